@@ -0,0 +1,344 @@
+use std::{
+    io::Cursor,
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+use crate::{config::ApiConfig, provider, Brightness, ChannelMsg, Statistic};
+
+/// How long text pushed to the display stays when no duration is given.
+const DEFAULT_TEXT_DURATION: Duration = Duration::from_secs(3);
+
+/// What the core currently does, kept up to date by the core for the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    /// The statistic selected in the tray or config.
+    pub statistic: Statistic,
+    /// The [`crate::config::CustomStatistic`] on the display instead of the
+    /// statistic, if any.
+    pub custom_statistic: Option<String>,
+    /// The statistic on the display, which differs from `statistic` while
+    /// rules or the rotation are enabled.
+    pub displayed: Statistic,
+    pub brightness: Brightness,
+    pub provider: &'static str,
+    pub display_connected: bool,
+    pub websocket_connected: bool,
+    /// Round trip time to the provider.
+    pub latency_ms: Option<u64>,
+}
+
+pub type SharedStatus = Arc<Mutex<Option<Status>>>;
+
+/// A request to the API, either from an HTTP route or a websocket message
+/// like `{"command": "set_statistic", "value": "accuracy"}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", content = "value", rename_all = "snake_case")]
+enum Command {
+    Status,
+    SetStatistic(Statistic),
+    SetBrightness(Brightness),
+    SetProvider(String),
+    ShowText {
+        text: String,
+        #[serde(default)]
+        duration_ms: Option<u64>,
+    },
+}
+
+/// The outcome of a [`Command`].
+enum Reply {
+    Status(Option<Status>),
+    Accepted,
+    BadRequest(String),
+}
+
+/// Serves the control API on localhost:
+///
+/// - `GET /status` answers with the [`Status`] as JSON.
+/// - `PUT /statistic`, `PUT /brightness` and `PUT /provider` take the new
+///   value as the body, spelled like in the config.
+/// - `POST /text?duration_ms=` shows the body on the display.
+/// - `GET /ws` upgrades to a websocket that takes a [`Command`] per message.
+///
+/// Requests from web pages are refused unless the page is served from
+/// localhost or one of the [`ApiConfig::allowed_origins`], so any site the
+/// user opens can't take over the display.
+pub struct Api {
+    tx: Sender<ChannelMsg>,
+    status: SharedStatus,
+    allowed_origins: Vec<String>,
+}
+
+impl Api {
+    pub fn spawn(
+        config: &ApiConfig,
+        tx: Sender<ChannelMsg>,
+        status: SharedStatus,
+    ) -> Option<JoinHandle<()>> {
+        let server = match Server::http(("127.0.0.1", config.port)) {
+            Ok(server) => server,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to start the control API on port {}: {e}",
+                    config.port
+                );
+                return None;
+            }
+        };
+
+        tracing::info!("Control API listening on http://127.0.0.1:{}", config.port);
+
+        let api = Api {
+            tx,
+            status,
+            allowed_origins: config.allowed_origins.clone(),
+        };
+        Some(std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                api.handle_request(request);
+            }
+        }))
+    }
+
+    fn handle_request(&self, mut request: Request) {
+        let (path, query) = match request.url().split_once('?') {
+            Some((path, query)) => (path.to_string(), query.to_string()),
+            None => (request.url().to_string(), String::new()),
+        };
+
+        if !self.origin_allowed(&request) {
+            let _ = request.respond(Response::empty(403));
+            return;
+        }
+
+        if *request.method() == Method::Get && path == "/ws" {
+            self.upgrade(request);
+            return;
+        }
+
+        let mut body = String::new();
+        if request.as_reader().read_to_string(&mut body).is_err() {
+            let _ = request.respond(Response::empty(400));
+            return;
+        }
+        let body = body.trim().to_string();
+
+        let command = match (request.method(), path.as_str()) {
+            (Method::Get, "/status") => Ok(Command::Status),
+            (Method::Put, "/statistic") => parse_value(body).map(Command::SetStatistic),
+            (Method::Put, "/brightness") => parse_value(body).map(Command::SetBrightness),
+            (Method::Put, "/provider") => Ok(Command::SetProvider(body)),
+            (Method::Post, "/text") => Ok(Command::ShowText {
+                text: body,
+                duration_ms: query_param(&query, "duration_ms").and_then(|ms| ms.parse().ok()),
+            }),
+            _ => {
+                let _ = request.respond(Response::empty(404));
+                return;
+            }
+        };
+
+        let reply = match command {
+            Ok(command) => self.execute(command),
+            Err(e) => Reply::BadRequest(e),
+        };
+
+        let _ = match reply {
+            Reply::Status(status) => request.respond(json_response(&status, 200)),
+            Reply::Accepted => request.respond(Response::empty(202)),
+            Reply::BadRequest(e) => request.respond(Response::from_string(e).with_status_code(400)),
+        };
+    }
+
+    /// Requests without an `Origin` don't come from a web page.
+    fn origin_allowed(&self, request: &Request) -> bool {
+        let Some(origin) = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Origin"))
+        else {
+            return true;
+        };
+        let origin = origin.value.as_str();
+
+        is_local_origin(origin) || self.allowed_origins.iter().any(|allowed| allowed == origin)
+    }
+
+    fn execute(&self, command: Command) -> Reply {
+        let msg = match command {
+            Command::Status => return Reply::Status(self.status.lock().unwrap().clone()),
+            Command::SetStatistic(statistic) => ChannelMsg::ChangeDisplayStat(statistic),
+            Command::SetBrightness(brightness) => ChannelMsg::ChangeDisplayBrightness(brightness),
+            Command::SetProvider(id) => match provider::find(&id) {
+                Some(provider) => ChannelMsg::ChangeServer(provider),
+                None => return Reply::BadRequest(format!("unknown provider `{id}`")),
+            },
+            Command::ShowText { text, duration_ms } => ChannelMsg::DisplayText(
+                text,
+                duration_ms.map_or(DEFAULT_TEXT_DURATION, Duration::from_millis),
+            ),
+        };
+
+        self.tx.send(msg).expect("Channel died");
+        Reply::Accepted
+    }
+
+    /// Turns the request into a websocket that takes a [`Command`] per message
+    /// and answers each with the status or an error.
+    fn upgrade(&self, request: Request) {
+        let key = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+            .map(|header| derive_accept_key(header.value.as_bytes()));
+
+        let Some(accept) = key else {
+            let _ = request.respond(Response::empty(400));
+            return;
+        };
+
+        let response = Response::new(
+            StatusCode(101),
+            vec![
+                Header::from_bytes("Upgrade", "websocket").unwrap(),
+                Header::from_bytes("Connection", "Upgrade").unwrap(),
+                Header::from_bytes("Sec-WebSocket-Accept", accept).unwrap(),
+            ],
+            Cursor::new(Vec::new()),
+            Some(0),
+            None,
+        );
+        let stream = request.upgrade("websocket", response);
+
+        let api = Api {
+            tx: self.tx.clone(),
+            status: self.status.clone(),
+            allowed_origins: self.allowed_origins.clone(),
+        };
+        std::thread::spawn(move || {
+            let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+            loop {
+                let text = match socket.read() {
+                    Ok(Message::Text(text)) => text,
+                    Ok(Message::Close(_)) | Err(_) => break,
+                    Ok(_) => continue,
+                };
+
+                let reply = match serde_json::from_str::<Command>(text.as_str()) {
+                    Ok(command) => match api.execute(command) {
+                        Reply::Status(status) => serde_json::json!({ "status": status }),
+                        Reply::Accepted => serde_json::json!({ "ok": true }),
+                        Reply::BadRequest(e) => serde_json::json!({ "error": e }),
+                    },
+                    Err(e) => serde_json::json!({ "error": e.to_string() }),
+                };
+
+                if socket
+                    .send(Message::Text(reply.to_string().into()))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Parses a bare value like `accuracy` the way the config file spells it.
+fn parse_value<T: for<'de> Deserialize<'de>>(value: String) -> Result<T, String> {
+    serde_json::from_value(Value::String(value)).map_err(|e| e.to_string())
+}
+
+/// Whether `origin`, like `http://localhost:3000`, is a page on this machine.
+fn is_local_origin(origin: &str) -> bool {
+    let Some(host) = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+    else {
+        return false;
+    };
+
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next(),
+        None => host.split(':').next(),
+    };
+
+    matches!(host, Some("localhost" | "127.0.0.1" | "::1"))
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+fn json_response(value: &impl Serialize, status: u16) -> Response<Cursor<Vec<u8>>> {
+    let body = serde_json::to_vec(value).expect("Failed to serialize response");
+
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        sync::mpsc,
+    };
+
+    use super::*;
+
+    /// Sends `PUT /brightness` from `origin` and returns the status line.
+    fn put_brightness(port: u16, origin: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "PUT /brightness HTTP/1.1\r\nHost: 127.0.0.1\r\nOrigin: {origin}\r\n\
+             Content-Length: 7\r\nConnection: close\r\n\r\nmaximum"
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn refuses_foreign_origins() {
+        let config = ApiConfig {
+            enabled: true,
+            port: 24071,
+            allowed_origins: vec![String::from("https://overlay.example")],
+        };
+        let (tx, rx) = mpsc::channel();
+        Api::spawn(&config, tx, SharedStatus::default()).unwrap();
+
+        assert!(put_brightness(config.port, "https://evil.example").contains("403"));
+        assert!(rx.try_recv().is_err());
+
+        assert!(put_brightness(config.port, "http://localhost:3000").contains("202"));
+        assert!(put_brightness(config.port, "https://overlay.example").contains("202"));
+        assert_eq!(rx.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn recognizes_local_origins() {
+        assert!(is_local_origin("http://localhost"));
+        assert!(is_local_origin("http://127.0.0.1:8080"));
+        assert!(is_local_origin("https://[::1]:8080"));
+        assert!(!is_local_origin("http://localhost.example"));
+        assert!(!is_local_origin("null"));
+    }
+}
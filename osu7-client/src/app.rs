@@ -4,12 +4,19 @@ use tao::{
     event::Event,
     event_loop::{ControlFlow, EventLoopBuilder},
 };
+use tracing_appender::non_blocking::WorkerGuard;
 use tray_icon::{
     menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu},
     TrayIconBuilder, TrayIconEvent,
 };
 
-use crate::ChannelMsg;
+use crate::{
+    config::Config,
+    logging,
+    provider::{self, DataProvider},
+    session::SessionSummary,
+    Brightness, ChannelMsg, Statistic,
+};
 
 const ICON_BUFFER: &[u8; 5169] = include_bytes!("../../assets/osu7_logo_trayicon.png");
 
@@ -23,7 +30,14 @@ enum AppEvent {
 }
 
 impl App {
-    pub fn run(tx: Sender<ChannelMsg>, rx: Receiver<ChannelMsg>) {
+    /// Runs the tray until the core confirms the display is off, then exits
+    /// the process. The `log_guard` is dropped right before, see [`logging::init`].
+    pub fn run(
+        tx: Sender<ChannelMsg>,
+        rx: Receiver<ChannelMsg>,
+        config: &Config,
+        mut log_guard: Option<WorkerGuard>,
+    ) -> ! {
         let event_loop = EventLoopBuilder::<AppEvent>::with_user_event().build();
 
         let proxy = event_loop.create_proxy();
@@ -53,46 +67,111 @@ impl App {
 
         let tray_menu = Menu::new();
 
+        // The selected item of each submenu is checked and disabled.
         let display_options = Submenu::new("Display", true);
-        let pp_ends_now_i = CheckMenuItem::new("PP (Current)", false, true, None);
-        let pp_if_fc_i = CheckMenuItem::new("PP (If FC)", true, false, None);
-        let acc_i = CheckMenuItem::new("Accuracy", true, false, None);
-        let ur_i = CheckMenuItem::new("Unstable Rate", true, false, None);
-
-        display_options
-            .append_items(&[&pp_ends_now_i, &pp_if_fc_i, &acc_i, &ur_i])
-            .unwrap();
+        let selected_custom = config.selected_custom_statistic();
+        let stat_items: Vec<(Statistic, CheckMenuItem)> = Statistic::ALL
+            .into_iter()
+            .map(|stat| {
+                let selected = selected_custom.is_none() && config.statistic == stat;
+                let item = CheckMenuItem::new(stat.name(), !selected, selected, None);
+                (stat, item)
+            })
+            .collect();
+
+        for (_, item) in &stat_items {
+            display_options.append(item).unwrap();
+        }
+
+        // Custom statistics from the config follow the built-in ones.
+        let custom_items: Vec<(String, CheckMenuItem)> = config
+            .custom_statistics
+            .iter()
+            .map(|custom| {
+                let selected = selected_custom.is_some_and(|selected| selected.name == custom.name);
+                let item = CheckMenuItem::new(&custom.name, !selected, selected, None);
+                (custom.name.clone(), item)
+            })
+            .collect();
+
+        if !custom_items.is_empty() {
+            display_options
+                .append(&PredefinedMenuItem::separator())
+                .unwrap();
+        }
+
+        for (_, item) in &custom_items {
+            display_options.append(item).unwrap();
+        }
 
         let brightness_options = Submenu::new("Brightness", true);
-        let min_brightness_i = CheckMenuItem::new("Minimum", true, false, None);
-        let med_brightness_i = CheckMenuItem::new("Medium", false, true, None);
-        let max_brightness_i = CheckMenuItem::new("Maximum", true, false, None);
-
-        brightness_options
-            .append_items(&[&max_brightness_i, &med_brightness_i, &min_brightness_i])
-            .unwrap();
+        let brightness_items: Vec<(Brightness, CheckMenuItem)> = [
+            ("Maximum", Brightness::Maximum),
+            ("Medium", Brightness::Medium),
+            ("Minimum", Brightness::Minimum),
+        ]
+        .into_iter()
+        .map(|(text, brightness)| {
+            let selected = config.brightness == brightness;
+            (
+                brightness,
+                CheckMenuItem::new(text, !selected, selected, None),
+            )
+        })
+        .collect();
+
+        for (_, item) in &brightness_items {
+            brightness_options.append(item).unwrap();
+        }
 
         let data_provider_options = Submenu::new("Data Provider", true);
-        let tosu_i = CheckMenuItem::new("Tosu", false, true, None);
-        let streamcompanion_i = CheckMenuItem::new("StreamCompanion", true, false, None);
+        let provider_items: Vec<(&'static dyn DataProvider, CheckMenuItem)> = provider::PROVIDERS
+            .iter()
+            .map(|&provider| {
+                let selected = config.provider == provider.id();
+                let item = CheckMenuItem::new(provider.name(), !selected, selected, None);
+                (provider, item)
+            })
+            .collect();
+
+        let custom_url_i = MenuItem::new("Custom URL…", true, None);
+
+        for (_, item) in &provider_items {
+            data_provider_options.append(item).unwrap();
+        }
 
         data_provider_options
-            .append_items(&[&tosu_i, &streamcompanion_i])
+            .append_items(&[&PredefinedMenuItem::separator(), &custom_url_i])
             .unwrap();
 
+        let pause_rotation_i =
+            CheckMenuItem::new("Pause Rotation", config.rotation.is_active(), false, None);
+
+        // Mirrors what the core knows, to prefill the custom URL dialog.
+        let mut config = config.clone();
+
+        let record_i = CheckMenuItem::new("Record Frames", true, false, None);
+        let open_logs_i = MenuItem::new("Open Log Folder", true, None);
         let quit_i = MenuItem::new("Quit", true, None);
         let ws_connected = CheckMenuItem::new("WebSocket Connected", false, false, None);
         let display_connected = CheckMenuItem::new("Display Connected", false, false, None);
+        let bad_frames = MenuItem::new("Bad Frames: 0", false, None);
+        let latency = MenuItem::new("Latency: -", false, None);
 
         tray_menu
             .append_items(&[
                 &ws_connected,
                 &display_connected,
+                &bad_frames,
+                &latency,
                 &PredefinedMenuItem::separator(),
                 &data_provider_options,
                 &display_options,
+                &pause_rotation_i,
                 &brightness_options,
                 &PredefinedMenuItem::separator(),
+                &record_i,
+                &open_logs_i,
                 &quit_i,
             ])
             .unwrap();
@@ -138,9 +217,41 @@ impl App {
                     ChannelMsg::WebsocketConnected(connected) => {
                         ws_connected.set_checked(connected);
                     }
+                    ChannelMsg::SessionSummary(summary) => {
+                        if let Some(tray_icon) = &tray_icon {
+                            let _ = tray_icon.set_tooltip(Some(Self::tooltip(&summary)));
+                        }
+                    }
+                    ChannelMsg::BadFrames(count) => {
+                        bad_frames.set_text(format!("Bad Frames: {count}"));
+                    }
+                    ChannelMsg::Latency(Some(rtt)) => {
+                        latency.set_text(format!("Latency: {} ms", rtt.as_millis()));
+                    }
+                    ChannelMsg::Latency(None) => latency.set_text("Latency: -"),
+                    // The core echoes changes, which may come from the API.
+                    ChannelMsg::ChangeDisplayStat(stat) => {
+                        Self::select(&stat_items, |other| *other == stat);
+                        Self::select(&custom_items, |_| false);
+                    }
+                    ChannelMsg::ChangeCustomStat(name) => {
+                        Self::select(&stat_items, |_| false);
+                        Self::select(&custom_items, |other| *other == name);
+                    }
+                    ChannelMsg::ChangeDisplayBrightness(brightness) => {
+                        Self::select(&brightness_items, |other| *other == brightness);
+                    }
+                    ChannelMsg::ChangeServer(provider) => {
+                        config.provider = provider.id().to_string();
+                        Self::select(&provider_items, |other| other.id() == provider.id());
+                    }
+                    ChannelMsg::Record(recording) => record_i.set_checked(recording),
                     ChannelMsg::AppExit => {
                         tray_icon.take();
                         *control_flow = ControlFlow::Exit;
+                        // Tao exits the process instead of returning, so
+                        // nothing else gets to flush the log file.
+                        drop(log_guard.take());
                     }
                     _ => {}
                 },
@@ -152,134 +263,83 @@ impl App {
 
                 Event::UserEvent(AppEvent::Menu(event)) => {
                     // Brightness
-                    if event.id == min_brightness_i.id() && min_brightness_i.is_checked() {
-                        med_brightness_i.set_checked(false);
-                        med_brightness_i.set_enabled(true);
-                        max_brightness_i.set_checked(false);
-                        max_brightness_i.set_enabled(true);
-
-                        min_brightness_i.set_enabled(false);
-
-                        tx.send(ChannelMsg::ChangeDisplayBrightness(
-                            crate::Brightness::Minimum,
-                        ))
-                        .expect("Channel died")
-                    }
-
-                    if event.id == med_brightness_i.id() && med_brightness_i.is_checked() {
-                        min_brightness_i.set_checked(false);
-                        min_brightness_i.set_enabled(true);
-                        max_brightness_i.set_checked(false);
-                        max_brightness_i.set_enabled(true);
+                    let brightness_event = brightness_items
+                        .iter()
+                        .find(|(_, item)| event.id == item.id() && item.is_checked());
 
-                        med_brightness_i.set_enabled(false);
+                    if let Some((brightness, _)) = brightness_event {
+                        Self::select(&brightness_items, |other| other == brightness);
 
-                        tx.send(ChannelMsg::ChangeDisplayBrightness(
-                            crate::Brightness::Medium,
-                        ))
-                        .expect("Channel died")
+                        tx.send(ChannelMsg::ChangeDisplayBrightness(*brightness))
+                            .expect("Channel died");
                     }
 
-                    if event.id == max_brightness_i.id() && max_brightness_i.is_checked() {
-                        min_brightness_i.set_checked(false);
-                        min_brightness_i.set_enabled(true);
-                        med_brightness_i.set_checked(false);
-                        med_brightness_i.set_enabled(true);
+                    // Settings
+                    let stat_event = stat_items
+                        .iter()
+                        .find(|(_, item)| event.id == item.id() && item.is_checked());
 
-                        max_brightness_i.set_enabled(false);
+                    if let Some((stat, _)) = stat_event {
+                        Self::select(&stat_items, |other| other == stat);
+                        Self::select(&custom_items, |_| false);
 
-                        tx.send(ChannelMsg::ChangeDisplayBrightness(
-                            crate::Brightness::Maximum,
-                        ))
-                        .expect("Channel died")
-                    }
-
-                    // Settings
-                    if event.id == pp_if_fc_i.id() && pp_if_fc_i.is_checked() {
-                        acc_i.set_checked(false);
-                        acc_i.set_enabled(true);
-                        pp_ends_now_i.set_checked(false);
-                        pp_ends_now_i.set_enabled(true);
-
-                        pp_if_fc_i.set_enabled(false);
-
-                        tx.send(ChannelMsg::ChangeDisplayStat(
-                            crate::Statistic::PerformanceFC,
-                        ))
-                        .expect("Channel died")
+                        tx.send(ChannelMsg::ChangeDisplayStat(*stat))
+                            .expect("Channel died");
                     }
 
-                    if event.id == pp_ends_now_i.id() && pp_ends_now_i.is_checked() {
-                        acc_i.set_checked(false);
-                        acc_i.set_enabled(true);
-                        pp_if_fc_i.set_checked(false);
-                        pp_if_fc_i.set_enabled(true);
-                        ur_i.set_checked(false);
-                        ur_i.set_enabled(true);
+                    let custom_event = custom_items
+                        .iter()
+                        .find(|(_, item)| event.id == item.id() && item.is_checked());
 
-                        pp_ends_now_i.set_enabled(false);
+                    if let Some((name, _)) = custom_event {
+                        Self::select(&stat_items, |_| false);
+                        Self::select(&custom_items, |other| other == name);
 
-                        tx.send(ChannelMsg::ChangeDisplayStat(
-                            crate::Statistic::PerformanceCurrent,
-                        ))
-                        .expect("Channel died")
+                        tx.send(ChannelMsg::ChangeCustomStat(name.clone()))
+                            .expect("Channel died");
                     }
 
-                    if event.id == acc_i.id() && acc_i.is_checked() {
-                        pp_ends_now_i.set_checked(false);
-                        pp_ends_now_i.set_enabled(true);
-                        pp_if_fc_i.set_checked(false);
-                        pp_if_fc_i.set_enabled(true);
-                        ur_i.set_checked(false);
-                        ur_i.set_enabled(true);
-
-                        acc_i.set_enabled(false);
-
-                        tx.send(ChannelMsg::ChangeDisplayStat(crate::Statistic::Accuracy))
-                            .expect("Channel died")
+                    if event.id == pause_rotation_i.id() {
+                        tx.send(ChannelMsg::PauseRotation(pause_rotation_i.is_checked()))
+                            .expect("Channel died");
                     }
 
-                    if event.id == ur_i.id() && ur_i.is_checked() {
-                        pp_ends_now_i.set_checked(false);
-                        pp_ends_now_i.set_enabled(true);
-                        pp_if_fc_i.set_checked(false);
-                        pp_if_fc_i.set_enabled(true);
-                        acc_i.set_checked(false);
-                        acc_i.set_enabled(true);
+                    // Data provider
+                    let provider_event = provider_items
+                        .iter()
+                        .find(|(_, item)| event.id == item.id() && item.is_checked());
 
-                        ur_i.set_enabled(false);
+                    if let Some((provider, _)) = provider_event {
+                        Self::select(&provider_items, |other| other.id() == provider.id());
 
-                        tx.send(ChannelMsg::ChangeDisplayStat(
-                            crate::Statistic::UnstableRate,
-                        ))
-                        .expect("Channel died")
+                        config.provider = provider.id().to_string();
+                        tx.send(ChannelMsg::ChangeServer(*provider))
+                            .expect("Channel died");
                     }
 
-                    if event.id == tosu_i.id() && tosu_i.is_checked() {
-                        streamcompanion_i.set_checked(false);
-                        streamcompanion_i.set_enabled(true);
+                    if event.id == custom_url_i.id() {
+                        let provider = config.provider();
 
-                        tosu_i.set_enabled(false);
+                        if let Some(url) = Self::ask_custom_url(&config.endpoint(provider).url()) {
+                            config.endpoint_mut(provider).url = url.clone();
+                            tx.send(ChannelMsg::ChangeServerUrl(provider, url))
+                                .expect("Channel died");
+                        }
+                    }
 
-                        tx.send(ChannelMsg::ChangeServer(crate::DataProviderServer::Tosu))
+                    if event.id == record_i.id() {
+                        tx.send(ChannelMsg::Record(record_i.is_checked()))
                             .expect("Channel died");
                     }
 
-                    if event.id == streamcompanion_i.id() && streamcompanion_i.is_checked() {
-                        tosu_i.set_checked(false);
-                        tosu_i.set_enabled(true);
-
-                        streamcompanion_i.set_enabled(false);
-
-                        tx.send(ChannelMsg::ChangeServer(
-                            crate::DataProviderServer::StreamCompanion,
-                        ))
-                        .expect("Channel died");
+                    if event.id == open_logs_i.id() {
+                        Self::open_logs();
                     }
 
                     // Exit
                     if event.id == quit_i.id() {
                         tx.send(ChannelMsg::AppExit).expect("Channel died");
+                        crate::force_exit_after(crate::SHUTDOWN_TIMEOUT);
                     }
                 }
 
@@ -288,6 +348,74 @@ impl App {
         });
     }
 
+    /// Checks and disables the selected item of a submenu, and unchecks and
+    /// enables the others.
+    fn select<T>(items: &[(T, CheckMenuItem)], is_selected: impl Fn(&T) -> bool) {
+        for (value, item) in items {
+            let selected = is_selected(value);
+            item.set_checked(selected);
+            item.set_enabled(!selected);
+        }
+    }
+
+    fn open_logs() {
+        let Some(dir) = logging::logs_dir() else {
+            return;
+        };
+
+        if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| open::that_detached(&dir)) {
+            tracing::warn!("Failed to open the log folder {}: {e}", dir.display());
+        }
+    }
+
+    /// Asks for the websocket URL of the selected provider. Returns `None` if
+    /// the dialog was cancelled, `Some(None)` if the override should be removed.
+    fn ask_custom_url(current: &str) -> Option<Option<String>> {
+        const TITLE: &str = "Osu7 - Custom URL";
+
+        let input = tinyfiledialogs::input_box(
+            TITLE,
+            "WebSocket URL of the selected data provider, e.g. ws://192.168.1.20:24050/ws.\n\
+             Leave empty to use the host, port and path from the config file.",
+            current,
+        )?;
+        let input = input.trim();
+
+        if input.is_empty() {
+            return Some(None);
+        }
+
+        if !input.starts_with("ws://") && !input.starts_with("wss://") {
+            tinyfiledialogs::message_box_ok(
+                TITLE,
+                "The URL has to start with ws:// or wss://.",
+                tinyfiledialogs::MessageBoxIcon::Error,
+            );
+            return None;
+        }
+
+        Some(Some(input.to_string()))
+    }
+
+    fn tooltip(summary: &SessionSummary) -> String {
+        let mut tooltip = format!(
+            "Osu7 Client\n{} plays, {:.0} pp total",
+            summary.plays, summary.total_pp
+        );
+
+        if let (Some(accuracy), Some(unstable_rate)) =
+            (summary.average_accuracy, summary.average_unstable_rate)
+        {
+            tooltip += &format!("\nAvg. {accuracy:.2}%, {unstable_rate:.0} UR");
+        }
+
+        if let Some(best_pp) = summary.best_pp {
+            tooltip += &format!("\nBest: {best_pp:.0} pp");
+        }
+
+        tooltip
+    }
+
     fn icon() -> tray_icon::Icon {
         let (icon_rgba, icon_width, icon_height) = {
             let image = image::load_from_memory(ICON_BUFFER)
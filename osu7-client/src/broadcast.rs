@@ -0,0 +1,151 @@
+use std::{
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+use tungstenite::Message;
+
+use crate::{config::BroadcastConfig, schema::OsuData, Statistic};
+
+/// How long a client may take to send its handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a blocking read may take before the client thread checks for
+/// frames to send, which delays them at most this long.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// What every client is sent whenever the data or the display changes.
+#[derive(Debug, Serialize)]
+pub struct Frame<'a> {
+    /// The [`crate::provider::DataProvider::id`] the data came from.
+    pub provider: &'a str,
+    /// The statistic on the display.
+    pub statistic: Statistic,
+    /// The [`crate::config::CustomStatistic::name`] shown instead of
+    /// `statistic`, if any.
+    pub custom_statistic: Option<&'a str>,
+    /// What the display shows, see [`crate::readout::Readout::digits`].
+    pub display: &'a str,
+    pub data: &'a OsuData,
+}
+
+#[derive(Default)]
+struct Clients {
+    senders: Vec<Sender<Arc<str>>>,
+    /// The latest frame, so new clients don't wait for a change.
+    last: Option<Arc<str>>,
+}
+
+/// Serves the normalized [`OsuData`] on a local websocket, so overlays see
+/// the same schema whichever provider is selected.
+pub struct Broadcast {
+    clients: Arc<Mutex<Clients>>,
+}
+
+impl Broadcast {
+    pub fn spawn(config: &BroadcastConfig) -> Option<Self> {
+        let listener = match TcpListener::bind(("127.0.0.1", config.port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::warn!("Failed to start the broadcast on port {}: {e}", config.port);
+                return None;
+            }
+        };
+
+        tracing::info!("Broadcasting data on ws://127.0.0.1:{}", config.port);
+
+        let clients = Arc::new(Mutex::new(Clients::default()));
+        let accepted = clients.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (tx, rx) = mpsc::channel::<Arc<str>>();
+                {
+                    let mut clients = accepted.lock().unwrap();
+                    if let Some(last) = &clients.last {
+                        let _ = tx.send(last.clone());
+                    }
+                    clients.senders.push(tx);
+                }
+
+                // Each client gets its own thread, so a slow one can't hold up
+                // the core or the clients after it.
+                std::thread::spawn(move || serve(stream, rx));
+            }
+        });
+
+        Some(Self { clients })
+    }
+
+    /// Sends `frame` to every client, unless it's the same as the last one.
+    pub fn send(&self, frame: &Frame) {
+        let json: Arc<str> = match serde_json::to_string(frame) {
+            Ok(json) => json.into(),
+            Err(e) => {
+                tracing::warn!("Failed to serialize broadcast frame: {e}");
+                return;
+            }
+        };
+
+        let mut clients = self.clients.lock().unwrap();
+        if clients.last.as_ref() == Some(&json) {
+            return;
+        }
+
+        clients
+            .senders
+            .retain(|sender| sender.send(json.clone()).is_ok());
+        clients.last = Some(json);
+    }
+}
+
+/// Sends the frames to one client until either side hangs up, answering its
+/// pings and close frame in between.
+fn serve(stream: TcpStream, frames: Receiver<Arc<str>>) {
+    if stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).is_err() {
+        return;
+    }
+
+    let Ok(mut socket) = tungstenite::accept(stream) else {
+        return;
+    };
+
+    if socket
+        .get_ref()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        loop {
+            match frames.try_recv() {
+                Ok(frame) => {
+                    if socket.send(Message::Text(frame.as_ref().into())).is_err() {
+                        return;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let _ = socket.close(None);
+                    let _ = socket.flush();
+                    return;
+                }
+            }
+        }
+
+        // Whatever the client sends is ignored, reading just lets tungstenite
+        // answer pings and the close handshake.
+        match socket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => return,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use mcp2221::Handle;
+use osu7_i2c::i2c_mock::I2cMock;
+
+use crate::config::DisplayBackend;
+
+/// The I2C bus the display hangs off, picked by [`DisplayBackend`].
+pub enum DisplayBus {
+    Mcp2221(Handle),
+    Mock(MockBus),
+}
+
+#[derive(Debug)]
+pub enum BusError {
+    Mcp2221(mcp2221::Error),
+    Mock,
+}
+
+impl fmt::Display for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BusError::Mcp2221(e) => write!(f, "MCP2221 error: {e:?}"),
+            BusError::Mock => write!(f, "mock bus error"),
+        }
+    }
+}
+
+/// An in-memory bus that keeps the display RAM around, shared between clones
+/// so it can be inspected while the core owns the display.
+#[derive(Clone, Default)]
+pub struct MockBus(Arc<Mutex<I2cMock>>);
+
+impl MockBus {
+    /// The display RAM as last written.
+    #[cfg(test)]
+    pub fn ram(&self) -> [u8; 16] {
+        self.0.lock().unwrap().data_values
+    }
+}
+
+impl DisplayBus {
+    /// Opens the bus of `backend`. The mock backend writes to `mock`.
+    pub fn open(backend: DisplayBackend, mock: &MockBus) -> Option<Self> {
+        match backend {
+            DisplayBackend::Mcp2221 => {
+                let config = mcp2221::Config::default();
+                Handle::open_first(&config).ok().map(DisplayBus::Mcp2221)
+            }
+            DisplayBackend::Mock => Some(DisplayBus::Mock(mock.clone())),
+        }
+    }
+}
+
+impl Write for DisplayBus {
+    type Error = BusError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        match self {
+            DisplayBus::Mcp2221(handle) => handle.write(address, bytes).map_err(BusError::Mcp2221),
+            DisplayBus::Mock(mock) => mock
+                .0
+                .lock()
+                .unwrap()
+                .write(address, bytes)
+                .map_err(|_| BusError::Mock),
+        }
+    }
+}
+
+impl WriteRead for DisplayBus {
+    type Error = BusError;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        match self {
+            DisplayBus::Mcp2221(handle) => handle
+                .write_read(address, bytes, buffer)
+                .map_err(BusError::Mcp2221),
+            DisplayBus::Mock(mock) => mock
+                .0
+                .lock()
+                .unwrap()
+                .write_read(address, bytes, buffer)
+                .map_err(|_| BusError::Mock),
+        }
+    }
+}
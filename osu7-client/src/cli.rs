@@ -0,0 +1,160 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::{builder::PossibleValuesParser, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    config::{Config, DisplayBackend, LogLevel},
+    history::{History, Play},
+    provider, schema, Brightness, Statistic,
+};
+
+/// Shows live osu! statistics on the Osu7 display.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Run without the tray icon, e.g. on a machine without a desktop.
+    #[arg(long, global = true)]
+    pub headless: bool,
+    /// Data provider to connect to.
+    #[arg(long, global = true, value_parser = PossibleValuesParser::new(provider::PROVIDERS.iter().map(|provider| provider.id())))]
+    pub provider: Option<String>,
+    /// Statistic to show.
+    #[arg(long, global = true, value_enum)]
+    pub statistic: Option<Statistic>,
+    #[arg(long, global = true, value_enum)]
+    pub brightness: Option<Brightness>,
+    /// How the display is reached.
+    #[arg(long, global = true, value_enum)]
+    pub backend: Option<DisplayBackend>,
+    /// Most verbose level of log messages to write.
+    #[arg(long, global = true, value_enum)]
+    pub log_level: Option<LogLevel>,
+    /// Record raw provider frames to the data directory.
+    #[arg(long, global = true)]
+    pub record: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Show statistics on the display, the default.
+    Run,
+    /// List or export recent plays from the local history.
+    History {
+        /// How many plays to show, newest first.
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+        /// Only show plays of the map with this MD5 checksum.
+        #[arg(short, long)]
+        map: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+        /// Write to a file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Show a recording made with `--record` as if the provider were live.
+    Replay {
+        file: PathBuf,
+        /// How many times faster than recorded to play it.
+        #[arg(short, long, default_value_t = 1.0)]
+        speed: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+impl Cli {
+    /// Applies the flags on top of the config file.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(provider) = &self.provider {
+            config.provider = provider.clone();
+        }
+        if let Some(statistic) = self.statistic {
+            config.statistic = statistic;
+        }
+        if let Some(brightness) = self.brightness {
+            config.brightness = brightness;
+        }
+        if let Some(backend) = self.backend {
+            config.display.backend = backend;
+        }
+        if let Some(level) = self.log_level {
+            config.log.level = level;
+        }
+    }
+}
+
+pub fn history(
+    limit: usize,
+    map: Option<String>,
+    format: Format,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    let history = History::open_default().map_err(|e| format!("Failed to open history: {e}"))?;
+    let plays = match map {
+        Some(map_hash) => history.plays_of_map(&map_hash, limit),
+        None => history.recent(limit),
+    }
+    .map_err(|e| format!("Failed to read history: {e}"))?;
+
+    let result = match output {
+        Some(path) => File::create(&path).and_then(|file| write_plays(file, &plays, format)),
+        None => write_plays(io::stdout().lock(), &plays, format),
+    };
+
+    result.map_err(|e| format!("Failed to write plays: {e}"))
+}
+
+fn write_plays(mut out: impl Write, plays: &[Play], format: Format) -> io::Result<()> {
+    match format {
+        Format::Table => {
+            writeln!(
+                out,
+                "{:<10} {:>9} {:<10} {:>8} {:>7} {:>7} {:>6}",
+                "timestamp", "map", "mods", "pp", "acc", "ur", "misses"
+            )?;
+
+            for play in plays {
+                let optional =
+                    |value: Option<u32>| value.map_or(String::from("-"), |v| v.to_string());
+
+                writeln!(
+                    out,
+                    "{:<10} {:>9} {:<10} {:>8.2} {:>7.2} {:>7.2} {:>6}",
+                    play.timestamp,
+                    optional(play.map_id),
+                    play.mods
+                        .map_or(String::from("-"), schema::mods_to_acronyms),
+                    play.pp,
+                    play.accuracy,
+                    play.unstable_rate,
+                    optional(play.misses),
+                )?;
+            }
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for play in plays {
+                writer.serialize(play)?;
+            }
+            writer.flush()?;
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, plays)?;
+            writeln!(out)?;
+        }
+    }
+
+    Ok(())
+}
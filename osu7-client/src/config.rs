@@ -0,0 +1,785 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    formula::Formula,
+    provider::{self, DataProvider},
+    schema::GameState,
+    Brightness, Statistic,
+};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Persistent client settings, stored as TOML in the platform config dir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub statistic: Statistic,
+    /// The [`CustomStatistic::name`] of the custom statistic shown instead of
+    /// `statistic`, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_statistic: Option<String>,
+    pub brightness: Brightness,
+    /// The [`DataProvider::id`] of the selected provider.
+    pub provider: String,
+    /// Endpoints by [`DataProvider::id`].
+    #[serde(deserialize_with = "deserialize_endpoints")]
+    pub endpoints: BTreeMap<String, Endpoint>,
+    /// Raw tokens to subscribe to on top of the ones the statistics need,
+    /// for providers that only send what was asked for.
+    pub extra_tokens: Vec<String>,
+    pub display: DisplayConfig,
+    pub rules: StatRules,
+    pub rotation: Rotation,
+    pub alerts: Alerts,
+    /// How personal best deltas show being behind.
+    pub delta_style: DeltaStyle,
+    pub smoothing: Smoothing,
+    pub api: ApiConfig,
+    pub broadcast: BroadcastConfig,
+    pub log: LogConfig,
+    pub custom_statistics: Vec<CustomStatistic>,
+    /// Where to connect instead of the endpoint of the provider, for this run
+    /// only, e.g. a [`crate::recording::Replay`].
+    #[serde(skip)]
+    pub connect_override: Option<String>,
+    /// Where to save recordings instead of
+    /// [`crate::recording::Recorder::recordings_dir`], for this run only.
+    #[serde(skip)]
+    pub recordings_dir: Option<PathBuf>,
+}
+
+/// A statistic computed from a [`Formula`], listed in the tray after the
+/// built-in ones. Ignored while the [`StatRules`] or the [`Rotation`] are
+/// enabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomStatistic {
+    pub name: String,
+    pub formula: Formula,
+    #[serde(default)]
+    pub format: CustomFormat,
+    /// How many decimals to show at most, fewer if the value doesn't fit.
+    #[serde(default)]
+    pub decimals: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomFormat {
+    /// A signed number.
+    #[default]
+    Number,
+    /// Milliseconds, like the time fields, shown as minutes and seconds.
+    Time,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Most verbose level of log messages to write.
+    pub level: LogLevel,
+    /// Also log into a file in the data dir, see [`crate::logging::logs_dir`].
+    pub file: bool,
+    /// How many daily log files to keep.
+    pub keep_files: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// The websocket serving the normalized data, see [`crate::broadcast`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BroadcastConfig {
+    pub enabled: bool,
+    /// Port on localhost to listen on.
+    pub port: u16,
+}
+
+/// The local control API, see [`crate::api`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    /// Port on localhost to listen on.
+    pub port: u16,
+    /// Origins of web pages besides localhost that may use the API, e.g.
+    /// `https://example.com`.
+    pub allowed_origins: Vec<String>,
+}
+
+/// Glides numeric readouts towards new values instead of snapping to them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Smoothing {
+    pub enabled: bool,
+    /// Time between two frames of a running tween. At least
+    /// [`Smoothing::MIN_FRAME`], see [`Smoothing::frame`].
+    pub frame_ms: u64,
+    /// The tween of each smoothed statistic, others snap.
+    pub statistics: BTreeMap<Statistic, TweenConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TweenConfig {
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    /// Fast at first, like a score counter.
+    #[default]
+    EaseOut,
+    EaseInOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeltaStyle {
+    /// Put a minus in front of the value.
+    Minus,
+    /// Blink the display, the value is shown without a sign.
+    Blink,
+}
+
+/// Cycles the display through several statistics, flashing a short label
+/// before each. Ignored while the [`StatRules`] are enabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rotation {
+    pub enabled: bool,
+    pub statistics: Vec<Statistic>,
+    /// How long each statistic is shown, label included. At least
+    /// [`Rotation::MIN_INTERVAL`], see [`Rotation::interval`].
+    pub interval_ms: u64,
+    /// How long the label is shown, 0 to skip it.
+    pub label_ms: u64,
+}
+
+/// How the display reacts to misses, slider breaks and combo breaks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Alerts {
+    pub enabled: bool,
+    pub on_miss: bool,
+    pub on_slider_break: bool,
+    /// Combo drops that weren't reported as a miss or slider break.
+    pub on_combo_break: bool,
+    pub kind: AlertKind,
+    pub duration_ms: u64,
+    /// Minimum time from the start of one alert to the next, so streams of
+    /// misses don't turn into constant flashing.
+    pub cooldown_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Blink the current value.
+    Blink,
+    /// Show what happened, e.g. `MISS`.
+    Text,
+    /// Turn the brightness all the way up, or down at maximum brightness.
+    Pulse,
+}
+
+/// Which statistic to show in which game state. When enabled, these replace
+/// the single [`Config::statistic`].
+///
+/// States without a rule fall back to the `idle` rule, which also applies
+/// while no provider is connected or the provider doesn't report the state.
+/// Watching a replay or spectating uses the `playing` rule. A rule set to
+/// `"none"` clears its default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatRules {
+    pub enabled: bool,
+    #[serde(with = "rule")]
+    pub menu: Option<Statistic>,
+    #[serde(with = "rule")]
+    pub editor: Option<Statistic>,
+    #[serde(with = "rule")]
+    pub song_select: Option<Statistic>,
+    #[serde(with = "rule")]
+    pub playing: Option<Statistic>,
+    #[serde(with = "rule")]
+    pub results: Option<Statistic>,
+    #[serde(with = "rule")]
+    pub multiplayer: Option<Statistic>,
+    #[serde(with = "rule")]
+    pub idle: Option<Statistic>,
+}
+
+/// Where a data provider can be reached.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+    /// Connect through `wss://` instead of `ws://`.
+    pub tls: bool,
+    /// A full URL that takes precedence over the fields above.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// An [`Endpoint`] as written in the config file, where missing fields are
+/// taken from the default endpoint of the provider.
+#[derive(Deserialize)]
+struct PartialEndpoint {
+    host: Option<String>,
+    port: Option<u16>,
+    path: Option<String>,
+    tls: Option<bool>,
+    url: Option<String>,
+}
+
+fn deserialize_endpoints<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Endpoint>, D::Error> {
+    let endpoints = BTreeMap::<String, PartialEndpoint>::deserialize(deserializer)?;
+
+    Ok(endpoints
+        .into_iter()
+        .map(|(id, endpoint)| {
+            let default = provider::find_or_default(&id).default_endpoint();
+            let endpoint = Endpoint {
+                host: endpoint.host.unwrap_or(default.host),
+                port: endpoint.port.unwrap_or(default.port),
+                path: endpoint.path.unwrap_or(default.path),
+                tls: endpoint.tls.unwrap_or(default.tls),
+                url: endpoint.url.or(default.url),
+            };
+            (id, endpoint)
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub backend: DisplayBackend,
+    pub address: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum DisplayBackend {
+    /// The MCP2221 USB to I2C bridge of the Osu7 board.
+    Mcp2221,
+    /// An in-memory bus, for running without the hardware.
+    Mock,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            statistic: Statistic::PerformanceCurrent,
+            custom_statistic: None,
+            brightness: Brightness::Medium,
+            provider: provider::PROVIDERS[0].id().to_string(),
+            endpoints: provider::PROVIDERS
+                .iter()
+                .map(|provider| (provider.id().to_string(), provider.default_endpoint()))
+                .collect(),
+            extra_tokens: Vec::new(),
+            display: DisplayConfig::default(),
+            rules: StatRules::default(),
+            rotation: Rotation::default(),
+            alerts: Alerts::default(),
+            delta_style: DeltaStyle::Minus,
+            smoothing: Smoothing::default(),
+            api: ApiConfig::default(),
+            broadcast: BroadcastConfig::default(),
+            log: LogConfig::default(),
+            custom_statistics: Vec::new(),
+            connect_override: None,
+            recordings_dir: None,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            file: true,
+            keep_files: 7,
+        }
+    }
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 24071,
+        }
+    }
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 24070,
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+impl Default for StatRules {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            menu: None,
+            editor: None,
+            song_select: Some(Statistic::StarRating),
+            playing: Some(Statistic::PerformanceCurrent),
+            results: Some(Statistic::Accuracy),
+            multiplayer: None,
+            idle: Some(Statistic::Clock),
+        }
+    }
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            on_miss: true,
+            on_slider_break: true,
+            on_combo_break: true,
+            kind: AlertKind::Text,
+            duration_ms: 600,
+            cooldown_ms: 3000,
+        }
+    }
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        let count_up = TweenConfig {
+            duration_ms: 600,
+            easing: Easing::EaseOut,
+        };
+        let glide = TweenConfig {
+            duration_ms: 300,
+            easing: Easing::EaseInOut,
+        };
+
+        Self {
+            enabled: false,
+            frame_ms: 33,
+            statistics: BTreeMap::from([
+                (Statistic::PerformanceCurrent, count_up),
+                (Statistic::PerformanceFC, count_up),
+                (Statistic::Accuracy, glide),
+            ]),
+        }
+    }
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            statistics: vec![
+                Statistic::PerformanceCurrent,
+                Statistic::Accuracy,
+                Statistic::UnstableRate,
+            ],
+            interval_ms: 5000,
+            label_ms: 750,
+        }
+    }
+}
+
+impl Smoothing {
+    /// About 60 frames per second, the display can't show more and 0 would
+    /// keep the core redrawing without a break.
+    pub const MIN_FRAME: Duration = Duration::from_millis(16);
+
+    /// Time between two frames of a running tween.
+    pub fn frame(&self) -> Duration {
+        Duration::from_millis(self.frame_ms).max(Self::MIN_FRAME)
+    }
+}
+
+impl Rotation {
+    /// Shorter intervals would make the display unreadable, and 0 would keep
+    /// the core rotating without a break.
+    pub const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Whether there is anything to rotate through.
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.statistics.is_empty()
+    }
+
+    /// How long each statistic is shown.
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms).max(Self::MIN_INTERVAL)
+    }
+}
+
+impl StatRules {
+    /// The rule for `state`, where `None` means idle.
+    pub fn statistic(&self, state: Option<GameState>) -> Option<Statistic> {
+        let rule = match state {
+            Some(GameState::Menu) => self.menu,
+            Some(GameState::Editor) => self.editor,
+            Some(GameState::SongSelect) => self.song_select,
+            Some(GameState::Playing | GameState::Watching) => self.playing,
+            Some(GameState::Results) => self.results,
+            Some(GameState::Multiplayer) => self.multiplayer,
+            None => None,
+        };
+
+        rule.or(self.idle)
+    }
+
+    fn statistics(&self) -> impl Iterator<Item = Statistic> {
+        [
+            self.menu,
+            self.editor,
+            self.song_select,
+            self.playing,
+            self.results,
+            self.multiplayer,
+            self.idle,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// (De)serializes a [`StatRules`] rule, where `"none"` stands for no rule so
+/// that it can't be mistaken for a missing one, which takes the default.
+mod rule {
+    use serde::{de::IntoDeserializer, Deserialize, Deserializer, Serializer};
+
+    use crate::Statistic;
+
+    const NONE: &str = "none";
+
+    pub fn serialize<S: Serializer>(
+        rule: &Option<Statistic>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match rule {
+            Some(statistic) => serde::Serialize::serialize(statistic, serializer),
+            None => serializer.serialize_str(NONE),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Statistic>, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        if name == NONE {
+            return Ok(None);
+        }
+
+        Statistic::deserialize(name.as_str().into_deserializer()).map(Some)
+    }
+}
+
+fn default_host() -> String {
+    String::from("localhost")
+}
+
+impl Endpoint {
+    pub fn local(port: u16, path: &str) -> Self {
+        Self {
+            host: default_host(),
+            port,
+            path: path.to_string(),
+            tls: false,
+            url: None,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        if let Some(url) = &self.url {
+            return url.clone();
+        }
+
+        let scheme = if self.tls { "wss" } else { "ws" };
+        format!("{scheme}://{}:{}{}", self.host, self.port, self.path)
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            backend: DisplayBackend::Mcp2221,
+            address: osu7_i2c::I2C_ADDR,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        // Tests change settings through the core, keep them off the user's.
+        if cfg!(test) {
+            return None;
+        }
+
+        dirs::config_dir().map(|dir| dir.join("osu7").join(CONFIG_FILE))
+    }
+
+    /// Loads the config file, or the defaults if there is none.
+    pub fn load() -> io::Result<Self> {
+        match Self::path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Only a missing file means defaults, one that can't be read may still
+    /// hold the user's settings.
+    fn load_from(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn provider(&self) -> &'static dyn DataProvider {
+        provider::find_or_default(&self.provider)
+    }
+
+    /// Every statistic the display may show with this config, along with the
+    /// ones the rules and alerts depend on.
+    pub fn statistics_in_use(&self) -> Vec<Statistic> {
+        let mut statistics = if self.rules.enabled {
+            let mut statistics: Vec<Statistic> = self.rules.statistics().collect();
+            // States without a rule fall back to it.
+            statistics.extend([self.statistic, Statistic::GameState]);
+            statistics
+        } else if self.rotation.is_active() {
+            self.rotation.statistics.clone()
+        } else if let Some(custom) = self.shown_custom_statistic() {
+            custom.formula.statistics()
+        } else {
+            vec![self.statistic]
+        };
+
+        if self.alerts.enabled {
+            statistics.extend([
+                Statistic::Misses,
+                Statistic::SliderBreaks,
+                Statistic::Combo,
+                Statistic::Score,
+                Statistic::GameState,
+            ]);
+        }
+
+        statistics
+    }
+
+    /// Raw provider tokens the display may need: the extra tokens and the ones
+    /// the custom statistic reads.
+    pub fn raw_tokens_in_use(&self) -> Vec<&str> {
+        let mut tokens: Vec<&str> = self.extra_tokens.iter().map(String::as_str).collect();
+
+        if let Some(custom) = self.shown_custom_statistic() {
+            tokens.extend(custom.formula.raw_tokens());
+        }
+
+        tokens
+    }
+
+    /// The selected custom statistic, if it is defined.
+    pub fn selected_custom_statistic(&self) -> Option<&CustomStatistic> {
+        let name = self.custom_statistic.as_ref()?;
+        self.custom_statistics
+            .iter()
+            .find(|custom| custom.name == *name)
+    }
+
+    /// The custom statistic to show, unless the rules or the rotation pick the
+    /// statistic instead.
+    pub fn shown_custom_statistic(&self) -> Option<&CustomStatistic> {
+        if self.rules.enabled || self.rotation.is_active() {
+            return None;
+        }
+
+        self.selected_custom_statistic()
+    }
+
+    /// The statistic to show in `state`, `None` meaning idle.
+    pub fn statistic_for(&self, state: Option<GameState>) -> Statistic {
+        if !self.rules.enabled {
+            return self.statistic;
+        }
+
+        self.rules.statistic(state).unwrap_or(self.statistic)
+    }
+
+    pub fn endpoint(&self, provider: &dyn DataProvider) -> Endpoint {
+        self.endpoints
+            .get(provider.id())
+            .cloned()
+            .unwrap_or_else(|| provider.default_endpoint())
+    }
+
+    pub fn endpoint_mut(&mut self, provider: &dyn DataProvider) -> &mut Endpoint {
+        self.endpoints
+            .entry(provider.id().to_string())
+            .or_insert_with(|| provider.default_endpoint())
+    }
+
+    /// Applies `change` to the config file and saves it. Only what `change`
+    /// touches ends up in the file, not the command line flags of this run.
+    pub fn update(change: impl FnOnce(&mut Self)) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        // Rather than losing the user's settings to the defaults.
+        let mut config = Self::load_from(&path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Can't read {}, not overwriting it: {e}", path.display()),
+            )
+        })?;
+        change(&mut config);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let contents = toml::to_string_pretty(&config).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_override_the_statistic() {
+        let mut config: Config = toml::from_str(
+            r#"
+            statistic = "unstable_rate"
+
+            [rules]
+            enabled = true
+            menu = "bpm"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.statistic_for(Some(GameState::SongSelect)),
+            Statistic::StarRating
+        );
+        assert_eq!(config.statistic_for(Some(GameState::Menu)), Statistic::Bpm);
+        assert_eq!(
+            config.statistic_for(Some(GameState::Editor)),
+            Statistic::Clock
+        );
+        assert_eq!(config.statistic_for(None), Statistic::Clock);
+
+        config.rules.enabled = false;
+        assert_eq!(
+            config.statistic_for(Some(GameState::Playing)),
+            Statistic::UnstableRate
+        );
+    }
+
+    #[test]
+    fn rules_can_be_cleared() {
+        let config: Config = toml::from_str(
+            r#"
+            [rules]
+            enabled = true
+            song_select = "none"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.rules.song_select, None);
+        assert_eq!(config.rules.playing, Some(Statistic::PerformanceCurrent));
+        assert_eq!(
+            config.statistic_for(Some(GameState::SongSelect)),
+            Statistic::Clock
+        );
+
+        let saved: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(saved.rules.song_select, None);
+        assert_eq!(saved.rules.menu, None);
+    }
+
+    #[test]
+    fn only_a_missing_file_loads_the_defaults() {
+        let dir = std::env::temp_dir().join(format!("osu7-{}-config", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert!(Config::load_from(&dir.join(CONFIG_FILE)).is_ok());
+        // Reading a directory fails like an unreadable file would.
+        assert!(Config::load_from(&dir).is_err());
+
+        fs::write(dir.join(CONFIG_FILE), "statistic = 5").unwrap();
+        let error = Config::load_from(&dir.join(CONFIG_FILE)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn partial_endpoints_use_the_provider_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [endpoints.tosu]
+            tls = true
+            "#,
+        )
+        .unwrap();
+
+        let tosu = provider::find("tosu").unwrap();
+        assert_eq!(
+            config.endpoint(tosu),
+            Endpoint {
+                tls: true,
+                ..tosu.default_endpoint()
+            }
+        );
+    }
+}
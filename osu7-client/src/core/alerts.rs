@@ -0,0 +1,85 @@
+use crate::schema::{GameState, OsuData};
+
+/// Something that went wrong in a play, worth an alert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choke {
+    Miss,
+    SliderBreak,
+    /// The combo dropped without a miss or slider break being reported,
+    /// e.g. by a provider that doesn't count them.
+    ComboBreak,
+}
+
+impl Choke {
+    /// Compares two successive snapshots of the same play.
+    pub fn detect(old: &OsuData, new: &OsuData) -> Option<Self> {
+        if !matches!(new.state(), Some(GameState::Playing) | None) || old.state() != new.state() {
+            return None;
+        }
+
+        // A retry resets every counter, that's not a choke.
+        if new.score() < old.score() || new.hits_300() < old.hits_300() {
+            return None;
+        }
+
+        let increased = |old: Option<u32>, new: Option<u32>| matches!((old, new), (Some(old), Some(new)) if new > old);
+        let decreased = |old: Option<u32>, new: Option<u32>| matches!((old, new), (Some(old), Some(new)) if new < old);
+
+        if increased(old.misses(), new.misses()) {
+            Some(Choke::Miss)
+        } else if increased(old.slider_breaks(), new.slider_breaks()) {
+            Some(Choke::SliderBreak)
+        } else if decreased(old.combo(), new.combo()) {
+            Some(Choke::ComboBreak)
+        } else {
+            None
+        }
+    }
+
+    /// What the text alert shows.
+    pub fn text(self) -> &'static str {
+        match self {
+            Choke::Miss => "MISS",
+            Choke::SliderBreak => "SB",
+            Choke::ComboBreak => "CB",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{provider::DataProvider, schema::streamcompanion::StreamCompanion};
+
+    fn frames(first: &str, second: &str) -> (OsuData, OsuData) {
+        let old = StreamCompanion
+            .parse(first.as_bytes(), &OsuData::default())
+            .unwrap();
+        let new = StreamCompanion.parse(second.as_bytes(), &old).unwrap();
+        (old, new)
+    }
+
+    #[test]
+    fn detects_misses_and_combo_breaks() {
+        let start = r#"{"status": 2, "score": 1000, "miss": 0, "combo": 120}"#;
+
+        let (old, new) = frames(start, r#"{"score": 1000, "miss": 1, "combo": 0}"#);
+        assert_eq!(Choke::detect(&old, &new), Some(Choke::Miss));
+
+        let (old, new) = frames(start, r#"{"score": 1200, "combo": 3}"#);
+        assert_eq!(Choke::detect(&old, &new), Some(Choke::ComboBreak));
+
+        let (old, new) = frames(start, r#"{"score": 1300, "combo": 121}"#);
+        assert_eq!(Choke::detect(&old, &new), None);
+    }
+
+    #[test]
+    fn retries_are_not_chokes() {
+        let (old, new) = frames(
+            r#"{"status": 2, "score": 1000, "miss": 0, "combo": 120}"#,
+            r#"{"score": 0, "combo": 0}"#,
+        );
+
+        assert_eq!(Choke::detect(&old, &new), None);
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::schema::DeserializeError;
+
+const SAMPLES_FILE: &str = "bad_frames.json";
+/// How many distinct offending payloads are kept for bug reports.
+const MAX_SAMPLES: usize = 16;
+/// Payloads are cut off after this many bytes.
+const MAX_PAYLOAD_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+struct Sample {
+    provider: &'static str,
+    error: String,
+    /// What samples are told apart by, see [`DeserializeError::without_position`].
+    #[serde(skip)]
+    kind: String,
+    /// Seconds since the unix epoch.
+    timestamp: u64,
+    payload: String,
+}
+
+/// Keeps count of provider frames that failed to deserialize, along with a
+/// sample of the payloads that caused it.
+#[derive(Debug, Default)]
+pub struct BadFrames {
+    count: u64,
+    samples: Vec<Sample>,
+}
+
+impl BadFrames {
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("osu7").join(SAMPLES_FILE))
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Records a bad frame. Returns `true` if it was a new kind of error and
+    /// its payload got sampled.
+    pub fn record(
+        &mut self,
+        provider: &'static str,
+        error: &DeserializeError,
+        payload: &str,
+    ) -> bool {
+        self.count += 1;
+
+        let kind = error.without_position();
+        if self.samples.len() >= MAX_SAMPLES || self.samples.iter().any(|s| s.kind == kind) {
+            return false;
+        }
+
+        let mut end = payload.len().min(MAX_PAYLOAD_LEN);
+        while !payload.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        self.samples.push(Sample {
+            provider,
+            error: error.to_string(),
+            kind,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            payload: payload[..end].to_string(),
+        });
+
+        if let Err(e) = self.save() {
+            tracing::warn!("Failed to save bad frame samples: {e}");
+        }
+
+        true
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let contents = serde_json::to_string_pretty(&self.samples).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+}
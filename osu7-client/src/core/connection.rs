@@ -0,0 +1,207 @@
+use std::{
+    io::ErrorKind,
+    net::TcpStream,
+    sync::mpsc::{self, Sender, TryRecvError},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+use super::CoreEvent;
+
+/// How long a blocking read may take before the socket thread checks for
+/// outgoing messages or a dropped [`Connection`].
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Time between two pings, which also measure the latency while data keeps
+/// coming in.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+/// How long the socket may be quiet before it is considered dead, pings
+/// included.
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug)]
+pub enum SocketEvent {
+    Connected,
+    /// A text or binary frame from the provider.
+    Message(Message),
+    /// Round trip time of the latest keepalive ping.
+    Latency(Duration),
+    Disconnected,
+}
+
+/// Handle to a websocket that lives on its own thread.
+///
+/// The thread reports everything it receives to the core as
+/// [`CoreEvent::Socket`], tagged with the id of the connection so events of an
+/// old connection can be told apart. Dropping the handle closes the socket.
+pub struct Connection {
+    id: u64,
+    outgoing: Sender<Message>,
+    thread: JoinHandle<()>,
+}
+
+impl Connection {
+    /// Connects to the first of `urls` that accepts a websocket.
+    pub fn open(
+        id: u64,
+        urls: Vec<String>,
+        handshake: Vec<String>,
+        events: Sender<CoreEvent>,
+    ) -> Self {
+        let (outgoing, outgoing_rx) = mpsc::channel::<Message>();
+
+        let thread = std::thread::spawn(move || {
+            let report = |event| events.send(CoreEvent::Socket(id, event)).is_ok();
+
+            let Some(mut socket) = urls.into_iter().find_map(|url| {
+                tungstenite::connect(&url)
+                    .inspect(|_| tracing::info!(url, "Connected to provider"))
+                    .inspect_err(|e| tracing::debug!(url, "Failed to connect to provider: {e}"))
+                    .ok()
+                    .map(|(socket, _)| socket)
+            }) else {
+                report(SocketEvent::Disconnected);
+                return;
+            };
+
+            for msg in handshake {
+                if socket.send(Message::Text(msg.into())).is_err() {
+                    report(SocketEvent::Disconnected);
+                    return;
+                }
+            }
+
+            set_read_timeout(&socket, POLL_INTERVAL);
+
+            if !report(SocketEvent::Connected) {
+                return;
+            }
+
+            let mut last_received = Instant::now();
+            let mut last_ping = Instant::now();
+            // Sequence number and send time of the unanswered ping.
+            let mut ping: Option<(u64, Instant)> = None;
+            let mut pings_sent = 0u64;
+
+            loop {
+                loop {
+                    match outgoing_rx.try_recv() {
+                        Ok(msg) => {
+                            if socket.send(msg).is_err() {
+                                report(SocketEvent::Disconnected);
+                                return;
+                            }
+                        }
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => {
+                            let _ = socket.close(None);
+                            let _ = socket.flush();
+                            return;
+                        }
+                    }
+                }
+
+                let quiet = last_received.elapsed();
+                if quiet >= READ_TIMEOUT {
+                    tracing::info!("Provider stopped responding for {quiet:?}, reconnecting");
+                    report(SocketEvent::Disconnected);
+                    return;
+                }
+
+                // An unanswered ping is replaced, its pong would only be late.
+                if last_ping.elapsed() >= KEEPALIVE_INTERVAL {
+                    last_ping = Instant::now();
+                    pings_sent += 1;
+                    let payload = pings_sent.to_be_bytes().to_vec();
+                    if socket.send(Message::Ping(payload.into())).is_err() {
+                        report(SocketEvent::Disconnected);
+                        return;
+                    }
+                    ping = Some((pings_sent, Instant::now()));
+                }
+
+                match socket.read() {
+                    Ok(msg) => {
+                        last_received = Instant::now();
+
+                        match msg {
+                            Message::Text(_) | Message::Binary(_) => {
+                                if !report(SocketEvent::Message(msg)) {
+                                    return;
+                                }
+                            }
+                            Message::Pong(payload) => {
+                                let answered =
+                                    ping.filter(|(sequence, _)| *payload == sequence.to_be_bytes());
+
+                                if let Some((_, sent)) = answered {
+                                    ping = None;
+                                    if !report(SocketEvent::Latency(sent.elapsed())) {
+                                        return;
+                                    }
+                                }
+                            }
+                            // Tungstenite answers pings on its own.
+                            Message::Ping(_) | Message::Frame(_) => {}
+                            Message::Close(frame) => {
+                                tracing::info!(?frame, "Provider closed the connection");
+                                // Reading on completes the closing handshake.
+                            }
+                        }
+                    }
+                    Err(tungstenite::Error::Io(e))
+                        if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                    Err(tungstenite::Error::ConnectionClosed) => {
+                        report(SocketEvent::Disconnected);
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::info!("Provider connection lost: {e}");
+                        report(SocketEvent::Disconnected);
+                        return;
+                    }
+                }
+            }
+        });
+
+        Self {
+            id,
+            outgoing,
+            thread,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Closes the socket and waits up to `timeout` for the close frame to go
+    /// out. A thread still busy connecting is left behind.
+    pub fn close(self, timeout: Duration) {
+        drop(self.outgoing);
+
+        let deadline = Instant::now() + timeout;
+        while !self.thread.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Queues a message, it is sent once the handshake is done.
+    pub fn send(&self, msg: Message) {
+        // The socket thread reports its own death, nothing to do here.
+        let _ = self.outgoing.send(msg);
+    }
+}
+
+fn set_read_timeout(socket: &WebSocket<MaybeTlsStream<TcpStream>>, timeout: Duration) {
+    let stream = match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream,
+        MaybeTlsStream::Rustls(stream) => stream.get_ref(),
+        _ => return,
+    };
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .expect("Failed to set read timeout");
+}
@@ -0,0 +1,1107 @@
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use alerts::Choke;
+use bad_frames::BadFrames;
+use connection::{Connection, SocketEvent};
+use osu7_i2c::{Dimming, Display, Osu7Display};
+use timers::{Timer, Timers};
+use tungstenite::Message;
+use tween::Tween;
+
+use crate::{
+    api::{SharedStatus, Status},
+    broadcast::{Broadcast, Frame},
+    bus::{BusError, DisplayBus, MockBus},
+    config::{AlertKind, Config, DeltaStyle},
+    history::{History, Play},
+    readout::{self, Inputs, Readout},
+    recording::Recorder,
+    schema::{GameState, OsuData},
+    session::Session,
+    Brightness, ChannelMsg, Statistic,
+};
+
+mod alerts;
+mod bad_frames;
+mod connection;
+mod timers;
+mod tween;
+
+/// Delay between attempts to reach the data provider.
+const SOCKET_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+/// Delay between attempts to open the MCP2221.
+const DISPLAY_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+/// How often readouts that change without new data, like the clock, are redrawn.
+const DISPLAY_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the socket thread gets to send the close frame on exit.
+const SOCKET_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long the display takes to fade out on exit.
+const FADE_OUT_DURATION: Duration = Duration::from_millis(300);
+
+/// Everything that can wake up the core thread.
+#[derive(Debug)]
+pub enum CoreEvent {
+    Command(ChannelMsg),
+    Socket(u64, SocketEvent),
+}
+
+pub struct Core {
+    tx: Sender<ChannelMsg>,
+    events: Sender<CoreEvent>,
+    timers: Timers,
+    socket: Option<Connection>,
+    socket_connected: bool,
+    /// What the provider was sent after connecting, see
+    /// [`crate::provider::DataProvider::handshake`].
+    handshake: Vec<String>,
+    connection_count: u64,
+    display: Option<Osu7Display<DisplayBus>>,
+    config: Config,
+    data: OsuData,
+    bad_frames: BadFrames,
+    /// Position in the rotation through [`crate::config::Rotation::statistics`].
+    rotation: usize,
+    rotation_paused: bool,
+    /// A rotation label is on the display, values wait until it's gone.
+    label_shown: bool,
+    /// When the last alert started, for rate limiting.
+    last_alert: Option<Instant>,
+    /// An alert is on the display, values wait until it's gone.
+    alert_shown: bool,
+    /// Where finished plays are recorded, if it could be opened.
+    history: Option<History>,
+    /// The best recorded play on the current map and mods, along with the map
+    /// checksum and mods it was looked up for.
+    personal_best: Option<Play>,
+    personal_best_key: Option<(String, Option<u32>)>,
+    /// The display blinks to show being behind the personal best.
+    blinking: bool,
+    session: Session,
+    /// The smoothed value of the statistic on the display.
+    tween: Option<(Statistic, Tween)>,
+    /// Text pushed through the API is on the display, values wait until it's gone.
+    text_shown: bool,
+    /// Where the core reports what it does, for the API.
+    status: SharedStatus,
+    broadcast: Option<Broadcast>,
+    /// Round trip time to the provider, once a keepalive ping was answered.
+    latency: Option<Duration>,
+    /// What the display is written to with the mock backend.
+    mock_bus: MockBus,
+    /// Where provider frames are recorded while recording.
+    recorder: Option<Recorder>,
+    /// What the display shows, see [`Readout::digits`].
+    shown: String,
+}
+
+impl Core {
+    pub fn run(
+        rx: Receiver<ChannelMsg>,
+        tx: Sender<ChannelMsg>,
+        config: Config,
+        status: SharedStatus,
+    ) -> JoinHandle<()> {
+        let history = History::open_default()
+            .inspect_err(|e| tracing::warn!("Play history unavailable: {e}"))
+            .ok();
+
+        Self::spawn(rx, tx, config, status, history, MockBus::default())
+    }
+
+    /// Like [`Core::run`], with the history and the bus of the mock backend
+    /// supplied by the caller.
+    pub fn spawn(
+        rx: Receiver<ChannelMsg>,
+        tx: Sender<ChannelMsg>,
+        config: Config,
+        status: SharedStatus,
+        history: Option<History>,
+        mock_bus: MockBus,
+    ) -> JoinHandle<()> {
+        let (events_tx, events_rx) = mpsc::channel();
+
+        // Forward commands from the app into the core's own event queue.
+        let commands = events_tx.clone();
+        std::thread::spawn(move || {
+            while let Ok(msg) = rx.recv() {
+                if commands.send(CoreEvent::Command(msg)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut instance = Core {
+            tx,
+            events: events_tx,
+            timers: Timers::default(),
+            socket: None,
+            socket_connected: false,
+            handshake: Vec::new(),
+            connection_count: 0,
+            display: None,
+            config,
+            data: OsuData::default(),
+            bad_frames: BadFrames::default(),
+            rotation: 0,
+            rotation_paused: false,
+            label_shown: false,
+            last_alert: None,
+            alert_shown: false,
+            history,
+            personal_best: None,
+            personal_best_key: None,
+            blinking: false,
+            session: Session::default(),
+            tween: None,
+            text_shown: false,
+            status,
+            broadcast: None,
+            latency: None,
+            mock_bus,
+            recorder: None,
+            shown: String::new(),
+        };
+
+        if instance.config.broadcast.enabled {
+            instance.broadcast = Broadcast::spawn(&instance.config.broadcast);
+        }
+        std::thread::spawn(move || {
+            Self::inner(&mut instance, events_rx);
+        })
+    }
+
+    pub fn connect(&mut self) {
+        let provider = self.config.provider();
+        self.handshake = provider.handshake(&self.config);
+
+        let urls = match &self.config.connect_override {
+            Some(url) => vec![url.clone()],
+            None => provider.urls(&self.config.endpoint(provider)),
+        };
+
+        self.connection_count += 1;
+        self.socket = Some(Connection::open(
+            self.connection_count,
+            urls,
+            self.handshake.clone(),
+            self.events.clone(),
+        ));
+    }
+
+    /// Repeats the handshake if the config change altered it, e.g. to
+    /// subscribe to the tokens a new statistic needs.
+    fn update_handshake(&mut self) {
+        let handshake = self.config.provider().handshake(&self.config);
+        if handshake == self.handshake {
+            return;
+        }
+
+        if let Some(socket) = &self.socket {
+            for msg in &handshake {
+                socket.send(Message::Text(msg.as_str().into()));
+            }
+        }
+
+        self.handshake = handshake;
+    }
+
+    fn disconnect(&mut self) {
+        self.socket = None;
+
+        if self.socket_connected {
+            tracing::info!(
+                provider = self.config.provider().id(),
+                "Provider disconnected"
+            );
+            self.socket_connected = false;
+            self.tx
+                .send(ChannelMsg::WebsocketConnected(false))
+                .expect("Channel died");
+
+            if self.latency.take().is_some() {
+                self.tx
+                    .send(ChannelMsg::Latency(None))
+                    .expect("Channel died");
+            }
+        }
+
+        // The rules may have something to show while idle.
+        if self.config.rules.enabled {
+            self.update_display();
+            return;
+        }
+
+        self.shown = readout::text_digits("OSU7");
+
+        if let Some(disp) = &mut self.display {
+            disp.device().clear_display_buffer();
+            if let Err(e) = disp.write_buffer_osu7().and_then(|_| disp.commit_buffer()) {
+                self.drop_display(e);
+            }
+        }
+    }
+
+    fn reconnect(&mut self) {
+        self.disconnect();
+        self.timers.schedule(Timer::ConnectSocket, Instant::now());
+    }
+
+    fn get_dimming(&self) -> Dimming {
+        match self.config.brightness {
+            Brightness::Minimum => Dimming::BRIGHTNESS_MIN,
+            Brightness::Medium => Dimming::BRIGHTNESS_8_16,
+            Brightness::Maximum => Dimming::BRIGHTNESS_MAX,
+        }
+    }
+
+    pub fn connect_display(&mut self) {
+        let backend = self.config.display.backend;
+        if let Some(bus) = DisplayBus::open(backend, &self.mock_bus) {
+            self.display = Some(Osu7Display::new(bus, self.config.display.address));
+
+            let dimming = self.get_dimming();
+
+            self.blinking = false;
+
+            self.shown = readout::text_digits("OSU7");
+
+            let disp = self.display.as_mut().unwrap();
+            if let Err(e) = disp
+                .initialize()
+                .and_then(|_| disp.device().set_dimming(dimming))
+            {
+                tracing::warn!("Failed to initialize the display: {e:?}");
+                self.display = None;
+                return;
+            }
+
+            tracing::info!(?backend, "Display connected");
+        } else {
+            tracing::debug!(?backend, "Display not found");
+            self.display = None;
+        }
+    }
+
+    fn drop_display(&mut self, error: BusError) {
+        tracing::warn!(%error, "Lost the display, reconnecting");
+        self.display = None;
+        self.tx
+            .send(ChannelMsg::DisplayConnected(false))
+            .expect("Channel died");
+        self.timers
+            .schedule_in(Timer::ConnectDisplay, DISPLAY_RETRY_INTERVAL);
+    }
+
+    pub fn inner(&mut self, events: Receiver<CoreEvent>) {
+        self.timers.schedule(Timer::ConnectDisplay, Instant::now());
+        self.timers.schedule(Timer::ConnectSocket, Instant::now());
+        self.schedule_rotation();
+
+        loop {
+            let event = match self.timers.next_deadline() {
+                Some(deadline) => {
+                    events.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match event {
+                Ok(CoreEvent::Command(ChannelMsg::AppExit)) => {
+                    self.shutdown();
+                    break;
+                }
+                Ok(CoreEvent::Command(msg)) => self.handle_command(msg),
+                Ok(CoreEvent::Socket(id, event)) => {
+                    // Events of a connection we already replaced are stale.
+                    if self.socket.as_ref().map(Connection::id) == Some(id) {
+                        self.handle_socket(event);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            for timer in self.timers.take_due(Instant::now()) {
+                self.handle_timer(timer);
+            }
+
+            self.publish_status();
+            self.broadcast();
+        }
+    }
+
+    fn broadcast(&self) {
+        if let Some(broadcast) = &self.broadcast {
+            broadcast.send(&Frame {
+                provider: self.config.provider().id(),
+                statistic: self.statistic(),
+                custom_statistic: self
+                    .config
+                    .shown_custom_statistic()
+                    .map(|custom| custom.name.as_str()),
+                display: &self.shown,
+                data: &self.data,
+            });
+        }
+    }
+
+    /// Writes `text` into the display buffer and remembers it as shown.
+    fn show_text(&mut self, text: &str) {
+        self.shown = readout::text_digits(text);
+
+        if let Some(disp) = &mut self.display {
+            disp.write_buffer_text(text);
+        }
+    }
+
+    /// Closes the socket, fades the display out and releases it, then tells
+    /// the app it can exit.
+    fn shutdown(&mut self) {
+        tracing::info!("Shutting down");
+
+        match self.session.save_report() {
+            Ok(Some(path)) => tracing::info!("Saved session report to {}", path.display()),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to save session report: {e}"),
+        }
+
+        if let Some(socket) = self.socket.take() {
+            socket.close(SOCKET_CLOSE_TIMEOUT);
+        }
+
+        self.stop_recording();
+
+        if let Some(mut disp) = self.display.take() {
+            let levels = match self.config.brightness {
+                Brightness::Minimum => 0,
+                Brightness::Medium => 7,
+                Brightness::Maximum => 15,
+            };
+
+            for level in (0..levels).rev() {
+                if let Ok(dimming) = Dimming::from_u8(level) {
+                    if disp.device().set_dimming(dimming).is_err() {
+                        break;
+                    }
+                }
+                std::thread::sleep(FADE_OUT_DURATION / u32::from(levels));
+            }
+
+            if let Err(e) = disp.shutdown() {
+                tracing::warn!("Failed to turn off the display: {e:?}");
+            }
+        }
+
+        self.tx.send(ChannelMsg::AppExit).expect("Channel died");
+    }
+
+    fn start_recording(&mut self) {
+        if self.recorder.is_none() {
+            match Recorder::create_default(self.config.recordings_dir.as_deref()) {
+                Ok(recorder) => {
+                    tracing::info!("Recording frames to {}", recorder.path().display());
+                    self.recorder = Some(recorder);
+                }
+                Err(e) => tracing::warn!("Failed to start recording: {e}"),
+            }
+        }
+
+        self.tx
+            .send(ChannelMsg::Record(self.recorder.is_some()))
+            .expect("Channel died");
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            match recorder.finish() {
+                Ok(path) => tracing::info!("Saved recording to {}", path.display()),
+                Err(e) => tracing::warn!("Failed to save recording: {e}"),
+            }
+
+            self.tx
+                .send(ChannelMsg::Record(false))
+                .expect("Channel died");
+        }
+    }
+
+    fn publish_status(&self) {
+        let status = Status {
+            statistic: self.config.statistic,
+            custom_statistic: self
+                .config
+                .shown_custom_statistic()
+                .map(|custom| custom.name.clone()),
+            displayed: self.statistic(),
+            brightness: self.config.brightness,
+            provider: self.config.provider().id(),
+            display_connected: self.display.is_some(),
+            websocket_connected: self.socket_connected,
+            latency_ms: self.latency.map(|latency| latency.as_millis() as u64),
+        };
+
+        *self.status.lock().unwrap() = Some(status);
+    }
+
+    fn handle_command(&mut self, msg: ChannelMsg) {
+        match msg {
+            ChannelMsg::ChangeDisplayStat(new_mode) => {
+                self.change_config(|config| {
+                    config.statistic = new_mode;
+                    config.custom_statistic = None;
+                });
+                self.update_handshake();
+                self.update_display();
+
+                // Keeps the tray in sync when the change came from the API.
+                self.tx
+                    .send(ChannelMsg::ChangeDisplayStat(new_mode))
+                    .expect("Channel died");
+            }
+            ChannelMsg::ChangeCustomStat(name) => {
+                if !self
+                    .config
+                    .custom_statistics
+                    .iter()
+                    .any(|custom| custom.name == name)
+                {
+                    tracing::warn!("Unknown custom statistic `{name}`");
+                    return;
+                }
+
+                self.change_config(|config| config.custom_statistic = Some(name.clone()));
+                self.update_handshake();
+                self.update_display();
+
+                self.tx
+                    .send(ChannelMsg::ChangeCustomStat(name))
+                    .expect("Channel died");
+            }
+            ChannelMsg::ChangeDisplayBrightness(brightness) => {
+                self.change_config(|config| config.brightness = brightness);
+                let dimming = self.get_dimming();
+
+                if let Some(disp) = &mut self.display {
+                    if let Err(e) = disp.device().set_dimming(dimming) {
+                        self.drop_display(e);
+                    }
+                }
+
+                self.tx
+                    .send(ChannelMsg::ChangeDisplayBrightness(brightness))
+                    .expect("Channel died");
+            }
+            ChannelMsg::DisplayText(text, duration) => {
+                self.text_shown = true;
+                self.timers.schedule_in(Timer::HideText, duration);
+                self.show_text(&text);
+
+                if let Some(disp) = &mut self.display {
+                    if let Err(e) = disp.commit_buffer() {
+                        self.drop_display(e);
+                    }
+                }
+            }
+            ChannelMsg::PauseRotation(paused) => {
+                self.rotation_paused = paused;
+
+                if paused {
+                    self.timers.cancel(Timer::Rotate);
+                } else {
+                    self.schedule_rotation();
+                }
+            }
+            ChannelMsg::Record(record) => {
+                if record {
+                    self.start_recording();
+                } else {
+                    self.stop_recording();
+                }
+            }
+            ChannelMsg::ChangeServer(new_server) => {
+                // A recording can only be replayed with a single provider.
+                if new_server.id() != self.config.provider {
+                    self.stop_recording();
+                }
+
+                self.config.connect_override = None;
+                self.change_config(|config| config.provider = new_server.id().to_string());
+                self.reconnect();
+
+                self.tx
+                    .send(ChannelMsg::ChangeServer(new_server))
+                    .expect("Channel died");
+            }
+            ChannelMsg::ChangeServerUrl(server, url) => {
+                self.config.connect_override = None;
+                self.change_config(|config| config.endpoint_mut(server).url = url.clone());
+
+                if server.id() == self.config.provider {
+                    self.reconnect();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies `change` to the config in use and to the config file, which
+    /// leaves the command line flags of this run out of the file.
+    fn change_config(&mut self, change: impl Fn(&mut Config)) {
+        change(&mut self.config);
+
+        if let Err(e) = Config::update(change) {
+            tracing::warn!("Failed to save config: {e}");
+        }
+    }
+
+    fn handle_timer(&mut self, timer: Timer) {
+        match timer {
+            Timer::ConnectDisplay => {
+                if self.display.is_some() {
+                    return;
+                }
+
+                self.connect_display();
+
+                if self.display.is_some() {
+                    self.tx
+                        .send(ChannelMsg::DisplayConnected(true))
+                        .expect("Channel died");
+
+                    if self.socket_connected || self.config.rules.enabled {
+                        self.update_display();
+                    }
+                } else {
+                    self.timers
+                        .schedule_in(Timer::ConnectDisplay, DISPLAY_RETRY_INTERVAL);
+                }
+            }
+            Timer::ConnectSocket => {
+                if self.socket.is_none() {
+                    self.connect();
+                }
+            }
+            Timer::RefreshDisplay => self.update_display(),
+            Timer::Rotate => self.rotate(),
+            Timer::HideLabel => {
+                self.label_shown = false;
+                self.update_display();
+            }
+            Timer::EndAlert => self.end_alert(),
+            Timer::HideText => {
+                self.text_shown = false;
+                self.update_display();
+            }
+            Timer::Frame => self.update_display(),
+        }
+    }
+
+    fn handle_socket(&mut self, event: SocketEvent) {
+        match event {
+            SocketEvent::Connected => {
+                self.socket_connected = true;
+                self.tx
+                    .send(ChannelMsg::WebsocketConnected(true))
+                    .expect("Channel died");
+            }
+            SocketEvent::Message(Message::Text(text)) => self.handle_frame(text.as_bytes()),
+            SocketEvent::Message(Message::Binary(bytes)) => self.handle_frame(&bytes),
+            SocketEvent::Message(_) => {}
+            SocketEvent::Latency(latency) => {
+                tracing::debug!(?latency, "Provider latency");
+                self.latency = Some(latency);
+                self.tx
+                    .send(ChannelMsg::Latency(Some(latency)))
+                    .expect("Channel died");
+            }
+            SocketEvent::Disconnected => {
+                self.disconnect();
+                self.timers
+                    .schedule_in(Timer::ConnectSocket, SOCKET_RETRY_INTERVAL);
+            }
+        }
+    }
+
+    /// Takes in a frame of provider data.
+    fn handle_frame(&mut self, frame: &[u8]) {
+        let provider = self.config.provider();
+
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.record(provider.id(), frame) {
+                tracing::warn!("Failed to record frame, stopping the recording: {e}");
+                self.stop_recording();
+            }
+        }
+
+        match provider.parse(frame, &self.data) {
+            Ok(data) => {
+                if data.state() != self.data.state() {
+                    tracing::info!(state = ?data.state(), "Game state changed");
+                }
+
+                let choke = Choke::detect(&self.data, &data);
+
+                if self.data.state() == Some(GameState::Playing)
+                    && data.state() == Some(GameState::Results)
+                {
+                    self.record_play(&data);
+                }
+
+                // The play may be the new best, but keep comparing against the
+                // old one until the results screen is left.
+                if self.data.state() == Some(GameState::Results)
+                    && data.state() != Some(GameState::Results)
+                {
+                    self.personal_best_key = None;
+                }
+
+                self.data = data;
+                self.update_personal_best();
+                self.update_display();
+
+                if let Some(choke) = choke {
+                    self.alert(choke);
+                }
+            }
+            // Skip the frame and keep showing the last good data.
+            Err(e) => {
+                if self
+                    .bad_frames
+                    .record(provider.id(), &e, &String::from_utf8_lossy(frame))
+                {
+                    tracing::warn!(provider = provider.id(), "Skipping bad frame: {e}");
+                } else {
+                    tracing::debug!(provider = provider.id(), "Skipping bad frame: {e}");
+                }
+
+                self.tx
+                    .send(ChannelMsg::BadFrames(self.bad_frames.count()))
+                    .expect("Channel died");
+            }
+        }
+    }
+
+    /// The statistic to show right now, following the rules or the rotation
+    /// if enabled.
+    fn statistic(&self) -> Statistic {
+        let rotation = &self.config.rotation;
+        if !self.config.rules.enabled && rotation.is_active() {
+            return rotation.statistics[self.rotation % rotation.statistics.len()];
+        }
+
+        let state = self.socket_connected.then(|| self.data.state()).flatten();
+        self.config.statistic_for(state)
+    }
+
+    fn schedule_rotation(&mut self) {
+        if self.config.rotation.is_active() && !self.rotation_paused {
+            self.timers
+                .schedule_in(Timer::Rotate, self.config.rotation.interval());
+        }
+    }
+
+    /// Shows the next statistic of the rotation, preceded by its label.
+    fn rotate(&mut self) {
+        // Don't let the label replace a text alert, try again once it's over.
+        if self.alert_shown {
+            let duration = Duration::from_millis(self.config.alerts.duration_ms);
+            self.timers.schedule_in(Timer::Rotate, duration);
+            return;
+        }
+
+        self.rotation = self.rotation.wrapping_add(1);
+        self.schedule_rotation();
+
+        // Keep the idle screen until there is data again.
+        if !self.socket_connected {
+            return;
+        }
+
+        let label_ms = self.config.rotation.label_ms;
+        if label_ms == 0 {
+            self.update_display();
+            return;
+        }
+
+        self.label_shown = true;
+        self.timers
+            .schedule_in(Timer::HideLabel, Duration::from_millis(label_ms));
+
+        self.show_text(self.statistic().label());
+
+        if let Some(disp) = &mut self.display {
+            if let Err(e) = disp.commit_buffer() {
+                self.drop_display(e);
+            }
+        }
+    }
+
+    fn record_play(&mut self, data: &OsuData) {
+        let play = Play::from_data(data);
+
+        if let Some(history) = &self.history {
+            match history.record(&play) {
+                Ok(()) => tracing::info!(map = ?play.map_id, pp = play.pp, "Recorded play"),
+                Err(e) => tracing::warn!("Failed to record play: {e}"),
+            }
+        }
+
+        self.session.record(play);
+        self.tx
+            .send(ChannelMsg::SessionSummary(self.session.summary()))
+            .expect("Channel died");
+    }
+
+    /// Looks up the personal best once the map or mods change.
+    fn update_personal_best(&mut self) {
+        let key = self
+            .data
+            .map_hash()
+            .map(|hash| (hash.to_string(), self.data.mods()));
+        if key == self.personal_best_key {
+            return;
+        }
+
+        self.personal_best = match (&self.history, &key) {
+            (Some(history), Some((hash, mods))) => history
+                .best_play(hash, *mods)
+                .inspect_err(|e| tracing::warn!("Failed to look up personal best: {e}"))
+                .ok()
+                .flatten(),
+            _ => None,
+        };
+        self.personal_best_key = key;
+    }
+
+    /// Runs the configured alert for `choke`, unless alerts are off for it or
+    /// the last one was too recent.
+    fn alert(&mut self, choke: Choke) {
+        let alerts = &self.config.alerts;
+        let wanted = match choke {
+            Choke::Miss => alerts.on_miss,
+            Choke::SliderBreak => alerts.on_slider_break,
+            Choke::ComboBreak => alerts.on_combo_break,
+        };
+
+        if !alerts.enabled || !wanted || self.alert_shown {
+            return;
+        }
+
+        let cooldown = Duration::from_millis(alerts.cooldown_ms);
+        if self
+            .last_alert
+            .is_some_and(|last| last.elapsed() < cooldown)
+        {
+            return;
+        }
+
+        let Some(disp) = &mut self.display else {
+            return;
+        };
+
+        let result = match alerts.kind {
+            AlertKind::Blink => disp.device().set_display(Display::TWO_HZ),
+            AlertKind::Text => {
+                self.alert_shown = true;
+                self.shown = readout::text_digits(choke.text());
+                disp.write_buffer_text(choke.text());
+                disp.commit_buffer()
+            }
+            AlertKind::Pulse => {
+                // Already as bright as it gets, so pulse the other way.
+                let dimming = match self.config.brightness {
+                    Brightness::Maximum => Dimming::BRIGHTNESS_MIN,
+                    _ => Dimming::BRIGHTNESS_MAX,
+                };
+                disp.device().set_dimming(dimming)
+            }
+        };
+
+        if let Err(e) = result {
+            self.alert_shown = false;
+            self.drop_display(e);
+            return;
+        }
+
+        self.last_alert = Some(Instant::now());
+        self.timers
+            .schedule_in(Timer::EndAlert, Duration::from_millis(alerts.duration_ms));
+    }
+
+    /// Undoes whatever the alert changed.
+    fn end_alert(&mut self) {
+        self.alert_shown = false;
+        let dimming = self.get_dimming();
+
+        if let Some(disp) = &mut self.display {
+            let result = match self.config.alerts.kind {
+                AlertKind::Blink => {
+                    // Let the update below restore blinking if it's wanted.
+                    self.blinking = false;
+                    disp.device().set_display(Display::ON)
+                }
+                AlertKind::Text => Ok(()),
+                AlertKind::Pulse => disp.device().set_dimming(dimming),
+            };
+
+            if let Err(e) = result {
+                self.drop_display(e);
+                return;
+            }
+        }
+
+        self.update_display();
+    }
+
+    /// Replaces the value of `readout` with the frame of its tween, and keeps
+    /// frames coming until the tween is done.
+    fn smooth(&mut self, statistic: Statistic, readout: Readout) -> Readout {
+        let smoothing = &self.config.smoothing;
+        let (Some(tween_config), Some(target)) = (
+            smoothing
+                .statistics
+                .get(&statistic)
+                .filter(|_| smoothing.enabled),
+            readout.value(),
+        ) else {
+            self.tween = None;
+            return readout;
+        };
+
+        let now = Instant::now();
+        let tween = match &mut self.tween {
+            Some((tweened, tween)) if *tweened == statistic => tween,
+            // Switching statistics snaps to the new one.
+            _ => &mut self.tween.insert((statistic, Tween::at(target))).1,
+        };
+
+        if tween.target() != target {
+            tween.retarget(
+                target,
+                now,
+                Duration::from_millis(tween_config.duration_ms),
+                tween_config.easing,
+            );
+        }
+
+        if !tween.is_done(now) {
+            self.timers.schedule_in(Timer::Frame, smoothing.frame());
+        }
+
+        readout.with_value(tween.value(now))
+    }
+
+    fn update_display(&mut self) {
+        if self.label_shown || self.alert_shown || self.text_shown {
+            return;
+        }
+
+        let statistic = self.statistic();
+        let custom = self
+            .config
+            .shown_custom_statistic()
+            .map(|custom| Readout::of_custom(custom, &self.data));
+        let is_custom = custom.is_some();
+
+        let mut readout = match custom {
+            Some(readout) => {
+                self.tween = None;
+                readout
+            }
+            None => {
+                let session = self.session.summary();
+                let readout = Readout::of(
+                    statistic,
+                    Inputs {
+                        data: &self.data,
+                        personal_best: self.personal_best.as_ref(),
+                        session: &session,
+                    },
+                );
+
+                self.smooth(statistic, readout)
+            }
+        };
+
+        // Only personal best deltas blink, custom statistics keep their sign.
+        let blink = match &mut readout {
+            Readout::Delta { value, .. }
+                if !is_custom && self.config.delta_style == DeltaStyle::Blink =>
+            {
+                let behind = *value < 0.0;
+                *value = value.abs();
+                behind
+            }
+            _ => false,
+        };
+
+        if !is_custom && statistic == Statistic::Clock {
+            self.timers
+                .schedule_in(Timer::RefreshDisplay, DISPLAY_REFRESH_INTERVAL);
+        }
+
+        self.shown = readout.digits();
+
+        let Some(disp) = &mut self.display else {
+            return;
+        };
+
+        readout.write(disp);
+
+        if blink != self.blinking {
+            let display = if blink { Display::TWO_HZ } else { Display::ON };
+            if let Err(e) = disp.device().set_display(display) {
+                self.drop_display(e);
+                return;
+            }
+            self.blinking = blink;
+        }
+
+        if let Err(e) = disp.commit_buffer() {
+            self.drop_display(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{render, MockServer, Step, TestCore};
+
+    fn tosu_payload(state: u8, pp: f64) -> Step {
+        let payload = serde_json::json!({
+            "menu": {"state": state, "bm": {"id": 1860433, "md5": "aa"}, "mods": {"num": 0}},
+            "gameplay": {
+                "pp": {"current": pp, "fc": 480.0},
+                "accuracy": 98.5,
+                "hits": {"unstableRate": 120.0, "0": 1},
+            },
+        });
+
+        Step::Send(payload.to_string())
+    }
+
+    #[test]
+    fn shows_tosu_data_on_the_display() {
+        let server = MockServer::tosu(vec![tosu_payload(2, 312.4)]);
+        let core = TestCore::start(server.config("tosu"));
+
+        assert!(core
+            .wait_for_msg(|msg| matches!(msg, ChannelMsg::WebsocketConnected(true)))
+            .is_some());
+        assert!(core.wait_for_ram(render(|disp| Readout::Integer(312).write(disp))));
+    }
+
+    #[test]
+    fn subscribes_to_streamcompanion_tokens() {
+        let server = MockServer::stream_companion(vec![Step::Send(String::from(
+            r#"{"acc": 98.5, "status": 2}"#,
+        ))]);
+        let core = TestCore::start(Config {
+            statistic: Statistic::Accuracy,
+            ..server.config("streamcompanion")
+        });
+
+        assert!(core.wait_for_ram(render(|disp| Readout::Decimal(98.5).write(disp))));
+
+        let tokens: Vec<String> = serde_json::from_str(&server.received()[0]).unwrap();
+        assert!(tokens.iter().any(|token| token == "acc"));
+    }
+
+    #[test]
+    fn shows_custom_statistics() {
+        let server = MockServer::tosu(vec![tosu_payload(2, 312.4)]);
+        let custom = toml::from_str(
+            r#"
+            name = "PP to FC"
+            formula = "pp_fc - pp_current"
+            decimals = 1
+            "#,
+        )
+        .unwrap();
+        let core = TestCore::start(Config {
+            custom_statistic: Some(String::from("PP to FC")),
+            custom_statistics: vec![custom],
+            ..server.config("tosu")
+        });
+
+        let expected = Readout::Delta {
+            value: 167.6,
+            decimals: 1,
+        };
+        assert!(core.wait_for_ram(render(|disp| expected.write(disp))));
+    }
+
+    #[test]
+    fn does_not_record_watched_plays() {
+        let frame = |status: u32, pp: f64| {
+            Step::Send(format!(
+                r#"{{"status": {status}, "ppIfMapEndsNow": {pp}, "md5": "aa", "mapid": 1}}"#
+            ))
+        };
+        let wait = || Step::Wait(Duration::from_millis(50));
+        let server = MockServer::stream_companion(vec![
+            frame(8, 250.0),
+            wait(),
+            frame(32, 250.0),
+            wait(),
+            frame(2, 120.0),
+            wait(),
+            frame(32, 150.0),
+        ]);
+        let core = TestCore::start(server.config("streamcompanion"));
+
+        let summary = core.wait_for_msg(|msg| matches!(msg, ChannelMsg::SessionSummary(_)));
+        let Some(ChannelMsg::SessionSummary(summary)) = summary else {
+            panic!("No session summary");
+        };
+
+        assert_eq!(summary.plays, 1);
+        assert_eq!(summary.best_pp, Some(150.0));
+    }
+
+    #[test]
+    fn stops_recording_when_the_provider_changes() {
+        let dir = std::env::temp_dir().join(format!("osu7-{}-recordings", std::process::id()));
+        let server = MockServer::tosu(vec![tosu_payload(2, 312.4)]);
+        let core = TestCore::start(Config {
+            recordings_dir: Some(dir.clone()),
+            ..server.config("tosu")
+        });
+
+        core.tx.send(ChannelMsg::Record(true)).unwrap();
+        assert!(core
+            .wait_for_msg(|msg| matches!(msg, ChannelMsg::Record(true)))
+            .is_some());
+
+        let other = crate::provider::find("streamcompanion").unwrap();
+        core.tx.send(ChannelMsg::ChangeServer(other)).unwrap();
+        assert!(core
+            .wait_for_msg(|msg| matches!(msg, ChannelMsg::Record(false)))
+            .is_some());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn records_finished_plays() {
+        let server = MockServer::tosu(vec![
+            tosu_payload(2, 250.0),
+            Step::Wait(Duration::from_millis(50)),
+            tosu_payload(7, 300.0),
+        ]);
+        let core = TestCore::start(server.config("tosu"));
+
+        let summary = core.wait_for_msg(|msg| matches!(msg, ChannelMsg::SessionSummary(_)));
+        let Some(ChannelMsg::SessionSummary(summary)) = summary else {
+            panic!("No session summary");
+        };
+
+        assert_eq!(summary.plays, 1);
+        assert_eq!(summary.best_pp, Some(300.0));
+        assert!(core.wait_for_ram(render(|disp| Readout::Integer(300).write(disp))));
+    }
+}
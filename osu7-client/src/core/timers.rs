@@ -0,0 +1,64 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Everything the core loop can be woken up for, besides channel messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timer {
+    ConnectSocket,
+    ConnectDisplay,
+    /// Redraws a readout that changes on its own, like the clock.
+    RefreshDisplay,
+    /// Moves on to the next statistic of the rotation.
+    Rotate,
+    /// Ends the label flash in front of a rotated statistic.
+    HideLabel,
+    /// Returns the display to normal after an alert.
+    EndAlert,
+    /// Draws the next frame of a running tween.
+    Frame,
+    /// Ends text pushed to the display through the API.
+    HideText,
+}
+
+/// A tiny deadline scheduler. Every [`Timer`] is pending at most once,
+/// scheduling it again moves its deadline.
+#[derive(Debug, Default)]
+pub struct Timers {
+    deadlines: HashMap<Timer, Instant>,
+}
+
+impl Timers {
+    pub fn schedule(&mut self, timer: Timer, at: Instant) {
+        self.deadlines.insert(timer, at);
+    }
+
+    pub fn schedule_in(&mut self, timer: Timer, delay: Duration) {
+        self.schedule(timer, Instant::now() + delay);
+    }
+
+    pub fn cancel(&mut self, timer: Timer) {
+        self.deadlines.remove(&timer);
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.values().min().copied()
+    }
+
+    /// Removes and returns every timer whose deadline has passed.
+    pub fn take_due(&mut self, now: Instant) -> Vec<Timer> {
+        let due: Vec<Timer> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(timer, _)| *timer)
+            .collect();
+
+        for timer in &due {
+            self.deadlines.remove(timer);
+        }
+
+        due
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+use crate::config::Easing;
+
+/// A value gliding from one number to another.
+#[derive(Debug, Clone, Copy)]
+pub struct Tween {
+    from: f64,
+    to: f64,
+    start: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Tween {
+    /// A tween that is already at `value`.
+    pub fn at(value: f64) -> Self {
+        Self {
+            from: value,
+            to: value,
+            start: Instant::now(),
+            duration: Duration::ZERO,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub fn target(&self) -> f64 {
+        self.to
+    }
+
+    /// Starts gliding from wherever the tween is at `now` towards `to`.
+    pub fn retarget(&mut self, to: f64, now: Instant, duration: Duration, easing: Easing) {
+        *self = Self {
+            from: self.value(now),
+            to,
+            start: now,
+            duration,
+            easing,
+        };
+    }
+
+    pub fn value(&self, now: Instant) -> f64 {
+        if self.is_done(now) {
+            return self.to;
+        }
+
+        let progress = now.duration_since(self.start).as_secs_f64() / self.duration.as_secs_f64();
+        self.from + (self.to - self.from) * self.easing.apply(progress)
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.start) >= self.duration
+    }
+}
+
+impl Easing {
+    /// Maps linear progress from 0 to 1 onto the curve.
+    fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glides_towards_the_target() {
+        let start = Instant::now();
+        let mut tween = Tween::at(312.0);
+        tween.retarget(480.0, start, Duration::from_millis(400), Easing::Linear);
+
+        assert_eq!(tween.value(start), 312.0);
+        assert_eq!(tween.value(start + Duration::from_millis(200)), 396.0);
+        assert_eq!(tween.value(start + Duration::from_millis(400)), 480.0);
+        assert!(tween.is_done(start + Duration::from_millis(400)));
+    }
+
+    #[test]
+    fn retargeting_starts_from_the_current_value() {
+        let start = Instant::now();
+        let mut tween = Tween::at(0.0);
+        tween.retarget(100.0, start, Duration::from_millis(100), Easing::Linear);
+
+        let now = start + Duration::from_millis(50);
+        tween.retarget(0.0, now, Duration::from_millis(100), Easing::EaseOut);
+
+        assert_eq!(tween.value(now), 50.0);
+        assert_eq!(tween.target(), 0.0);
+    }
+}
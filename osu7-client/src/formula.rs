@@ -0,0 +1,647 @@
+//! A small expression language for custom statistics, e.g.
+//! `pp_fc - pp_current` or `if(time_elapsed > 0, misses / (time_elapsed / 60000), 0)`.
+//!
+//! Formulas work on numbers. They read the fields of [`OsuData`] by name and
+//! raw provider tokens as `raw.<token>`, and support `+ - * / %`, comparisons,
+//! `&& || !`, and the functions `min`, `max`, `round`, `floor`, `ceil`, `abs`
+//! and `if(condition, then, else)`. Comparisons and logic give 1 or 0, and any
+//! number other than 0 counts as true. Times, like `time_elapsed`, are in
+//! milliseconds.
+//!
+//! A formula has no value while a field it needs isn't reported, or when it
+//! divides by zero.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{
+    schema::{GameState, OsuData},
+    Statistic,
+};
+
+/// A field of [`OsuData`] a formula can read, along with the statistic whose
+/// provider token it comes from.
+struct Field {
+    name: &'static str,
+    statistic: Statistic,
+    read: fn(&OsuData) -> Option<f64>,
+}
+
+const FIELDS: &[Field] = &[
+    Field {
+        name: "pp_current",
+        statistic: Statistic::PerformanceCurrent,
+        read: |data| Some(data.pp_current()),
+    },
+    Field {
+        name: "pp_fc",
+        statistic: Statistic::PerformanceFC,
+        read: |data| Some(data.pp_fc()),
+    },
+    Field {
+        name: "accuracy",
+        statistic: Statistic::Accuracy,
+        read: |data| Some(data.accuracy()),
+    },
+    Field {
+        name: "unstable_rate",
+        statistic: Statistic::UnstableRate,
+        read: |data| Some(data.unstable_rate()),
+    },
+    Field {
+        name: "combo",
+        statistic: Statistic::Combo,
+        read: |data| data.combo().map(f64::from),
+    },
+    Field {
+        name: "max_combo",
+        statistic: Statistic::MaxCombo,
+        read: |data| data.max_combo().map(f64::from),
+    },
+    Field {
+        name: "hits_300",
+        statistic: Statistic::Hits300,
+        read: |data| data.hits_300().map(f64::from),
+    },
+    Field {
+        name: "hits_100",
+        statistic: Statistic::Hits100,
+        read: |data| data.hits_100().map(f64::from),
+    },
+    Field {
+        name: "hits_50",
+        statistic: Statistic::Hits50,
+        read: |data| data.hits_50().map(f64::from),
+    },
+    Field {
+        name: "misses",
+        statistic: Statistic::Misses,
+        read: |data| data.misses().map(f64::from),
+    },
+    Field {
+        name: "slider_breaks",
+        statistic: Statistic::SliderBreaks,
+        read: |data| data.slider_breaks().map(f64::from),
+    },
+    Field {
+        name: "score",
+        statistic: Statistic::Score,
+        read: |data| data.score().map(|score| score as f64),
+    },
+    Field {
+        name: "hp",
+        statistic: Statistic::Health,
+        read: OsuData::hp,
+    },
+    Field {
+        name: "time_elapsed",
+        statistic: Statistic::TimeElapsed,
+        read: OsuData::time_elapsed,
+    },
+    Field {
+        name: "time_total",
+        statistic: Statistic::TimeTotal,
+        read: OsuData::time_total,
+    },
+    Field {
+        name: "star_rating",
+        statistic: Statistic::StarRating,
+        read: OsuData::star_rating,
+    },
+    Field {
+        name: "bpm",
+        statistic: Statistic::Bpm,
+        read: OsuData::bpm,
+    },
+    Field {
+        name: "mods",
+        statistic: Statistic::Mods,
+        read: |data| data.mods().map(f64::from),
+    },
+    // 1 during a play, 0 otherwise.
+    Field {
+        name: "playing",
+        statistic: Statistic::GameState,
+        read: |data| {
+            data.state()
+                .map(|state| f64::from(u8::from(state == GameState::Playing)))
+        },
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Min,
+    Max,
+    Round,
+    Floor,
+    Ceil,
+    Abs,
+    If,
+}
+
+impl Function {
+    fn find(name: &str) -> Option<Self> {
+        Some(match name {
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "round" => Function::Round,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "abs" => Function::Abs,
+            "if" => Function::If,
+            _ => return None,
+        })
+    }
+
+    /// Whether the function takes `count` arguments.
+    fn takes(self, count: usize) -> bool {
+        match self {
+            Function::Min | Function::Max => count >= 1,
+            // The optional second argument is the number of decimals.
+            Function::Round => count == 1 || count == 2,
+            Function::Floor | Function::Ceil | Function::Abs => count == 1,
+            Function::If => count == 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    /// An index into [`FIELDS`].
+    Field(usize),
+    Raw(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+/// A formula that failed to parse. Positions are byte offsets.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    UnexpectedChar(usize, char),
+    UnexpectedToken(usize, String),
+    UnexpectedEnd,
+    UnknownName(usize, String),
+    WrongArguments(usize, String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedChar(at, c) => write!(f, "unexpected `{c}` at {at}"),
+            ParseError::UnexpectedToken(at, token) => write!(f, "unexpected `{token}` at {at}"),
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of formula"),
+            ParseError::UnknownName(at, name) => write!(f, "unknown name `{name}` at {at}"),
+            ParseError::WrongArguments(at, name) => {
+                write!(f, "wrong number of arguments to `{name}` at {at}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{number}"),
+            Token::Name(name) => write!(f, "{name}"),
+            Token::Symbol(symbol) => write!(f, "{symbol}"),
+        }
+    }
+}
+
+/// Longest first, so `<=` isn't read as `<`.
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "==", "!=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "(", ")", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+
+    loop {
+        rest = rest.trim_start();
+        let at = source.len() - rest.len();
+        let Some(c) = rest.chars().next() else {
+            return Ok(tokens);
+        };
+
+        let length = if c.is_ascii_digit() || c == '.' {
+            let length = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = rest[..length]
+                .parse()
+                .map_err(|_| ParseError::UnexpectedToken(at, rest[..length].to_string()))?;
+            tokens.push((at, Token::Number(number)));
+            length
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.')
+                .unwrap_or(rest.len());
+            tokens.push((at, Token::Name(rest[..length].to_string())));
+            length
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push((at, Token::Symbol(symbol)));
+            symbol.len()
+        } else {
+            return Err(ParseError::UnexpectedChar(at, c));
+        };
+
+        rest = &rest[length..];
+    }
+}
+
+/// A recursive descent parser, one method per level of precedence.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), ParseError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(ParseError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it's `symbol`.
+    fn eat(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        self.position += usize::from(found);
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), ParseError> {
+        match self.next()? {
+            (_, Token::Symbol(s)) if s == symbol => Ok(()),
+            (at, token) => Err(ParseError::UnexpectedToken(at, token.to_string())),
+        }
+    }
+
+    /// Parses operands separated by any of `ops`, left to right.
+    fn binary(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        operand: fn(&mut Self) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        let mut expr = operand(self)?;
+
+        'outer: loop {
+            for (symbol, op) in ops {
+                if self.eat(symbol) {
+                    expr = Expr::Binary(*op, Box::new(expr), Box::new(operand(self)?));
+                    continue 'outer;
+                }
+            }
+            return Ok(expr);
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[("&&", BinaryOp::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            &[
+                ("<=", BinaryOp::Le),
+                (">=", BinaryOp::Ge),
+                ("==", BinaryOp::Eq),
+                ("!=", BinaryOp::Ne),
+                ("<", BinaryOp::Lt),
+                (">", BinaryOp::Gt),
+            ],
+            Self::sum,
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        self.binary(&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+            Self::unary,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat("-") {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.next()? {
+            (_, Token::Number(number)) => Ok(Expr::Number(number)),
+            (_, Token::Symbol("(")) => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            (at, Token::Name(name)) if self.eat("(") => {
+                let function =
+                    Function::find(&name).ok_or(ParseError::UnknownName(at, name.clone()))?;
+
+                let mut arguments = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        arguments.push(self.or()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+
+                if !function.takes(arguments.len()) {
+                    return Err(ParseError::WrongArguments(at, name));
+                }
+
+                Ok(Expr::Call(function, arguments))
+            }
+            (at, Token::Name(name)) => {
+                if let Some(token) = name.strip_prefix("raw.").filter(|token| !token.is_empty()) {
+                    return Ok(Expr::Raw(token.to_string()));
+                }
+
+                FIELDS
+                    .iter()
+                    .position(|field| field.name == name)
+                    .map(Expr::Field)
+                    .ok_or(ParseError::UnknownName(at, name))
+            }
+            (at, token) => Err(ParseError::UnexpectedToken(at, token.to_string())),
+        }
+    }
+}
+
+/// A parsed formula, stored in the config as its source.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Formula {
+    source: String,
+    expr: Expr,
+}
+
+impl Formula {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+
+        let expr = parser.or()?;
+        if let Some((at, token)) = parser.tokens.get(parser.position) {
+            return Err(ParseError::UnexpectedToken(*at, token.to_string()));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    /// The value of the formula for `data`, `None` if it has none.
+    pub fn eval(&self, data: &OsuData) -> Option<f64> {
+        eval(&self.expr, data).filter(|value| value.is_finite())
+    }
+
+    /// The statistics whose provider tokens the formula reads.
+    pub fn statistics(&self) -> Vec<Statistic> {
+        let mut statistics = Vec::new();
+        visit(&self.expr, &mut |expr| {
+            if let Expr::Field(index) = expr {
+                statistics.push(FIELDS[*index].statistic);
+            }
+        });
+        statistics
+    }
+
+    /// The raw provider tokens the formula reads.
+    pub fn raw_tokens(&self) -> Vec<&str> {
+        let mut tokens = Vec::new();
+        visit(&self.expr, &mut |expr| {
+            if let Expr::Raw(token) = expr {
+                tokens.push(token.as_str());
+            }
+        });
+        tokens
+    }
+}
+
+impl TryFrom<String> for Formula {
+    type Error = ParseError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Self::parse(&source)
+    }
+}
+
+impl Serialize for Formula {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+fn visit<'a>(expr: &'a Expr, f: &mut impl FnMut(&'a Expr)) {
+    f(expr);
+
+    match expr {
+        Expr::Neg(inner) | Expr::Not(inner) => visit(inner, f),
+        Expr::Binary(_, left, right) => {
+            visit(left, f);
+            visit(right, f);
+        }
+        Expr::Call(_, arguments) => arguments.iter().for_each(|argument| visit(argument, f)),
+        Expr::Number(_) | Expr::Field(_) | Expr::Raw(_) => {}
+    }
+}
+
+fn truth(condition: bool) -> f64 {
+    f64::from(u8::from(condition))
+}
+
+fn raw_value(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        Value::Bool(flag) => Some(truth(*flag)),
+        _ => None,
+    }
+}
+
+fn eval(expr: &Expr, data: &OsuData) -> Option<f64> {
+    let value = match expr {
+        Expr::Number(number) => *number,
+        Expr::Field(index) => (FIELDS[*index].read)(data)?,
+        Expr::Raw(token) => raw_value(data.raw().get(token)?)?,
+        Expr::Neg(inner) => -eval(inner, data)?,
+        Expr::Not(inner) => truth(eval(inner, data)? == 0.0),
+        // Only evaluate the right side if it matters, like `if`.
+        Expr::Binary(BinaryOp::And, left, right) => {
+            truth(eval(left, data)? != 0.0 && eval(right, data)? != 0.0)
+        }
+        Expr::Binary(BinaryOp::Or, left, right) => {
+            truth(eval(left, data)? != 0.0 || eval(right, data)? != 0.0)
+        }
+        Expr::Binary(op, left, right) => {
+            let (left, right) = (eval(left, data)?, eval(right, data)?);
+
+            match op {
+                BinaryOp::Add => left + right,
+                BinaryOp::Sub => left - right,
+                BinaryOp::Mul => left * right,
+                BinaryOp::Div | BinaryOp::Rem if right == 0.0 => return None,
+                BinaryOp::Div => left / right,
+                BinaryOp::Rem => left % right,
+                BinaryOp::Lt => truth(left < right),
+                BinaryOp::Le => truth(left <= right),
+                BinaryOp::Gt => truth(left > right),
+                BinaryOp::Ge => truth(left >= right),
+                BinaryOp::Eq => truth(left == right),
+                BinaryOp::Ne => truth(left != right),
+                BinaryOp::And | BinaryOp::Or => unreachable!("Handled above"),
+            }
+        }
+        Expr::Call(Function::If, arguments) => {
+            let branch = if eval(&arguments[0], data)? != 0.0 {
+                &arguments[1]
+            } else {
+                &arguments[2]
+            };
+            eval(branch, data)?
+        }
+        Expr::Call(function, arguments) => {
+            let values = arguments
+                .iter()
+                .map(|argument| eval(argument, data))
+                .collect::<Option<Vec<f64>>>()?;
+
+            match function {
+                Function::Min => values.into_iter().fold(f64::INFINITY, f64::min),
+                Function::Max => values.into_iter().fold(f64::NEG_INFINITY, f64::max),
+                Function::Round => {
+                    let scale = 10f64.powi(values.get(1).copied().unwrap_or(0.0) as i32);
+                    (values[0] * scale).round() / scale
+                }
+                Function::Floor => values[0].floor(),
+                Function::Ceil => values[0].ceil(),
+                Function::Abs => values[0].abs(),
+                Function::If => unreachable!("Handled above"),
+            }
+        }
+    };
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{provider::DataProvider, schema::streamcompanion::StreamCompanion};
+
+    fn data(payload: &str) -> OsuData {
+        StreamCompanion
+            .parse(payload.as_bytes(), &OsuData::default())
+            .unwrap()
+    }
+
+    fn eval(source: &str, data: &OsuData) -> Option<f64> {
+        Formula::parse(source).unwrap().eval(data)
+    }
+
+    #[test]
+    fn evaluates_formulas() {
+        let data = data(
+            r#"{"ppIfMapEndsNow": 120.0, "ppIfRestFced": 180.5, "acc": 97.5,
+                "miss": 3, "time": 90.0, "status": 2, "mapStrains": "12.5"}"#,
+        );
+
+        assert_eq!(eval("pp_fc - pp_current", &data), Some(60.5));
+        assert_eq!(eval("2 + 3 * 4 - -1", &data), Some(15.0));
+        assert_eq!(eval("round(accuracy / 100, 1)", &data), Some(1.0));
+        assert_eq!(eval("max(misses, 5, 1) + min(2, 8)", &data), Some(7.0));
+        assert_eq!(eval("if(playing && misses > 0, 1, 2)", &data), Some(1.0));
+        assert_eq!(eval("raw.mapStrains * 2", &data), Some(25.0));
+        assert_eq!(eval("misses / (time_elapsed / 60000)", &data), Some(2.0));
+
+        // Missing fields and division by zero have no value, unless skipped.
+        assert_eq!(eval("raw.unknown + 1", &data), None);
+        assert_eq!(eval("1 / (misses - 3)", &data), None);
+        assert_eq!(eval("if(misses == 3, 0, 1 / 0)", &data), Some(0.0));
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert_eq!(
+            Formula::parse("pp + 1"),
+            Err(ParseError::UnknownName(0, String::from("pp")))
+        );
+        assert_eq!(
+            Formula::parse("round()"),
+            Err(ParseError::WrongArguments(0, String::from("round")))
+        );
+        assert_eq!(Formula::parse("(accuracy"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(
+            Formula::parse("misses # 2"),
+            Err(ParseError::UnexpectedChar(7, '#'))
+        );
+        assert_eq!(
+            Formula::parse("misses 2"),
+            Err(ParseError::UnexpectedToken(7, String::from("2")))
+        );
+    }
+}
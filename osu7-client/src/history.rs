@@ -0,0 +1,211 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::schema::OsuData;
+
+const HISTORY_FILE: &str = "history.sqlite";
+
+/// A finished play, as stored in the history.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Play {
+    /// Seconds since the unix epoch.
+    pub timestamp: i64,
+    pub map_id: Option<u32>,
+    pub map_hash: Option<String>,
+    pub mods: Option<u32>,
+    pub pp: f64,
+    pub accuracy: f64,
+    pub unstable_rate: f64,
+    pub misses: Option<u32>,
+    pub max_combo: Option<u32>,
+    pub score: Option<u64>,
+}
+
+impl Play {
+    /// Takes the final values of a play from the data on its results screen.
+    pub fn from_data(data: &OsuData) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+            map_id: data.map_id(),
+            map_hash: data.map_hash().map(str::to_string),
+            mods: data.mods(),
+            pp: data.pp_current(),
+            accuracy: data.accuracy(),
+            unstable_rate: data.unstable_rate(),
+            misses: data.misses(),
+            max_combo: data.max_combo(),
+            score: data.score(),
+        }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            timestamp: row.get("timestamp")?,
+            map_id: row.get("map_id")?,
+            map_hash: row.get("map_hash")?,
+            mods: row.get("mods")?,
+            pp: row.get("pp")?,
+            accuracy: row.get("accuracy")?,
+            unstable_rate: row.get("unstable_rate")?,
+            misses: row.get("misses")?,
+            max_combo: row.get("max_combo")?,
+            score: row
+                .get::<_, Option<i64>>("score")?
+                .map(|score| score as u64),
+        })
+    }
+}
+
+/// The local database of finished plays.
+#[derive(Debug)]
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("osu7").join(HISTORY_FILE))
+    }
+
+    /// Opens the history at its default location, creating it if needed.
+    pub fn open_default() -> rusqlite::Result<Self> {
+        let path = Self::path().ok_or(rusqlite::Error::InvalidPath(PathBuf::from(HISTORY_FILE)))?;
+
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        Self::open(&path)
+    }
+
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS plays (
+                id INTEGER PRIMARY KEY,
+                timestamp INTEGER NOT NULL,
+                map_id INTEGER,
+                map_hash TEXT,
+                mods INTEGER,
+                pp REAL NOT NULL,
+                accuracy REAL NOT NULL,
+                unstable_rate REAL NOT NULL,
+                misses INTEGER,
+                max_combo INTEGER,
+                score INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS plays_map_hash ON plays (map_hash);",
+        )?;
+
+        Ok(Self { conn })
+    }
+
+    pub fn record(&self, play: &Play) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "INSERT INTO plays (
+                timestamp, map_id, map_hash, mods, pp, accuracy, unstable_rate,
+                misses, max_combo, score
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                play.timestamp,
+                play.map_id,
+                play.map_hash,
+                play.mods,
+                play.pp,
+                play.accuracy,
+                play.unstable_rate,
+                play.misses,
+                play.max_combo,
+                play.score.map(|score| score as i64),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// The latest `limit` plays, newest first.
+    pub fn recent(&self, limit: usize) -> rusqlite::Result<Vec<Play>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT * FROM plays ORDER BY timestamp DESC, id DESC LIMIT ?1")?;
+        let plays = statement.query_map([limit as i64], Play::from_row)?;
+        plays.collect()
+    }
+
+    /// The latest `limit` plays of the map with the given checksum, newest first.
+    pub fn plays_of_map(&self, map_hash: &str, limit: usize) -> rusqlite::Result<Vec<Play>> {
+        let mut statement = self.conn.prepare(
+            "SELECT * FROM plays WHERE map_hash = ?1 ORDER BY timestamp DESC, id DESC LIMIT ?2",
+        )?;
+        let plays = statement.query_map(params![map_hash, limit as i64], Play::from_row)?;
+        plays.collect()
+    }
+
+    /// The play with the most pp on the map with the given checksum and mods.
+    pub fn best_play(&self, map_hash: &str, mods: Option<u32>) -> rusqlite::Result<Option<Play>> {
+        self.conn
+            .query_row(
+                "SELECT * FROM plays WHERE map_hash = ?1 AND mods IS ?2
+                 ORDER BY pp DESC, accuracy DESC LIMIT 1",
+                params![map_hash, mods],
+                Play::from_row,
+            )
+            .optional()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(timestamp: i64, map_hash: &str, pp: f64) -> Play {
+        Play {
+            timestamp,
+            map_id: Some(1860433),
+            map_hash: Some(map_hash.to_string()),
+            mods: Some(72),
+            pp,
+            accuracy: 97.5,
+            unstable_rate: 120.0,
+            misses: Some(1),
+            max_combo: Some(1200),
+            score: Some(12_345_678_901),
+        }
+    }
+
+    #[test]
+    fn records_and_queries_plays() {
+        let history = History::open_in_memory().unwrap();
+        let first = play(100, "aa", 300.0);
+        let second = play(200, "bb", 150.0);
+        let third = play(300, "aa", 320.0);
+
+        for play in [&first, &second, &third] {
+            history.record(play).unwrap();
+        }
+
+        assert_eq!(history.recent(2).unwrap(), [third.clone(), second]);
+        assert_eq!(
+            history.plays_of_map("aa", 10).unwrap(),
+            [third.clone(), first]
+        );
+        assert_eq!(history.best_play("aa", Some(72)).unwrap(), Some(third));
+        assert_eq!(history.best_play("aa", None).unwrap(), None);
+    }
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::PathBuf};
+
+use tracing::level_filters::LevelFilter;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+
+use crate::config::LogConfig;
+
+const LOGS_DIR: &str = "logs";
+
+pub fn logs_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("osu7").join(LOGS_DIR))
+}
+
+/// Logs to stdout and, if enabled, to a file in [`logs_dir`] that rotates
+/// daily. Keep the returned guard until exiting, dropping it writes the lines
+/// still in flight, which are lost if it never is.
+pub fn init(config: &LogConfig) -> Option<WorkerGuard> {
+    let level = LevelFilter::from_level(config.level.into());
+
+    let (file, guard) = match config.file.then(logs_dir).flatten().map(|dir| {
+        // The appender prunes old files before creating the directory.
+        let _ = fs::create_dir_all(&dir);
+
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("osu7")
+            .filename_suffix("log")
+            .max_log_files(config.keep_files.max(1))
+            .build(dir)
+    }) {
+        Some(Ok(appender)) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(level);
+            (Some(layer), Some(guard))
+        }
+        Some(Err(e)) => {
+            eprintln!("Failed to open the log file: {e}");
+            (None, None)
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(level))
+        .with(file)
+        .init();
+
+    guard
+}
@@ -4,77 +4,346 @@
     windows_subsystem = "windows"
 )]
 
+use api::Api;
 use app::App;
+use clap::{Parser, ValueEnum};
+use cli::{Cli, Command};
+use config::Config;
 use core::Core;
-use schema::{streamcompanion::StreamCompanionResponse, tosu::TosuResponse, OsuData};
-use std::sync::mpsc;
+use provider::DataProvider;
+use recording::Replay;
+use serde::{Deserialize, Serialize};
+use session::SessionSummary;
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
 
+mod api;
 mod app;
+mod broadcast;
+mod bus;
+mod cli;
+mod config;
 mod core;
+mod formula;
+mod history;
+mod logging;
+mod provider;
+mod readout;
+mod recording;
 mod schema;
+mod session;
+#[cfg(test)]
+mod test_support;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum ChannelMsg {
     ChangeDisplayStat(Statistic),
+    /// Shows the [`config::CustomStatistic`] with this name instead of the
+    /// statistic.
+    ChangeCustomStat(String),
     ChangeDisplayBrightness(Brightness),
-    ChangeServer(DataProviderServer),
+    ChangeServer(&'static dyn DataProvider),
+    /// Overrides the endpoint of a provider with a full URL, `None` restores
+    /// the host, port and path from the config.
+    ChangeServerUrl(&'static dyn DataProvider, Option<String>),
     DisplayConnected(bool),
     WebsocketConnected(bool),
+    /// Pauses or resumes the rotation through statistics.
+    PauseRotation(bool),
+    /// The session changed, for the tray tooltip.
+    SessionSummary(SessionSummary),
+    /// Number of provider frames that failed to deserialize so far.
+    BadFrames(u64),
+    /// Round trip time to the provider, `None` while unknown.
+    Latency(Option<Duration>),
+    /// Starts or stops recording provider frames, see [`recording::Recorder`].
+    Record(bool),
+    /// Shows text on the display for a while instead of the statistic.
+    DisplayText(String, Duration),
     AppExit,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
 enum Statistic {
+    #[serde(rename = "performance_fc")]
+    #[value(name = "performance_fc")]
     PerformanceFC,
     PerformanceCurrent,
     Accuracy,
     UnstableRate,
+    Combo,
+    MaxCombo,
+    Hits300,
+    Hits100,
+    Hits50,
+    Misses,
+    SliderBreaks,
+    Score,
+    Health,
+    TimeElapsed,
+    TimeTotal,
+    StarRating,
+    Bpm,
+    Mods,
+    GameMode,
+    GameState,
+    /// pp the play ends with if the rest is full combo, or its final pp on
+    /// the results screen, minus the final pp of the personal best on the map
+    /// with the same mods.
+    PbDeltaPerformance,
+    /// Accuracy minus the accuracy of the personal best on the map with the
+    /// same mods.
+    PbDeltaAccuracy,
+    /// Plays finished since the client was started.
+    SessionPlays,
+    /// Sum of the pp of this session's plays.
+    SessionPerformance,
+    SessionAccuracy,
+    SessionUnstableRate,
+    /// pp of the best play of the session.
+    SessionBest,
+    /// The local time of day.
+    Clock,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+impl Statistic {
+    /// Every statistic, in tray menu order.
+    const ALL: [Statistic; 28] = [
+        Statistic::PerformanceCurrent,
+        Statistic::PerformanceFC,
+        Statistic::Accuracy,
+        Statistic::UnstableRate,
+        Statistic::Combo,
+        Statistic::MaxCombo,
+        Statistic::Hits300,
+        Statistic::Hits100,
+        Statistic::Hits50,
+        Statistic::Misses,
+        Statistic::SliderBreaks,
+        Statistic::Score,
+        Statistic::Health,
+        Statistic::TimeElapsed,
+        Statistic::TimeTotal,
+        Statistic::StarRating,
+        Statistic::Bpm,
+        Statistic::Mods,
+        Statistic::GameMode,
+        Statistic::GameState,
+        Statistic::PbDeltaPerformance,
+        Statistic::PbDeltaAccuracy,
+        Statistic::SessionPlays,
+        Statistic::SessionPerformance,
+        Statistic::SessionAccuracy,
+        Statistic::SessionUnstableRate,
+        Statistic::SessionBest,
+        Statistic::Clock,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Statistic::PerformanceFC => "PP (If FC)",
+            Statistic::PerformanceCurrent => "PP (Current)",
+            Statistic::Accuracy => "Accuracy",
+            Statistic::UnstableRate => "Unstable Rate",
+            Statistic::Combo => "Combo",
+            Statistic::MaxCombo => "Max Combo",
+            Statistic::Hits300 => "300s",
+            Statistic::Hits100 => "100s",
+            Statistic::Hits50 => "50s",
+            Statistic::Misses => "Misses",
+            Statistic::SliderBreaks => "Slider Breaks",
+            Statistic::Score => "Score",
+            Statistic::Health => "HP",
+            Statistic::TimeElapsed => "Time (Elapsed)",
+            Statistic::TimeTotal => "Time (Total)",
+            Statistic::StarRating => "Star Rating",
+            Statistic::Bpm => "BPM",
+            Statistic::Mods => "Mods",
+            Statistic::GameMode => "Game Mode",
+            Statistic::GameState => "Game State",
+            Statistic::PbDeltaPerformance => "PP vs. Best",
+            Statistic::PbDeltaAccuracy => "Accuracy vs. Best",
+            Statistic::SessionPlays => "Session: Plays",
+            Statistic::SessionPerformance => "Session: Total PP",
+            Statistic::SessionAccuracy => "Session: Accuracy",
+            Statistic::SessionUnstableRate => "Session: Unstable Rate",
+            Statistic::SessionBest => "Session: Best PP",
+            Statistic::Clock => "Clock",
+        }
+    }
+
+    /// A short label for the display, flashed before the value while rotating.
+    fn label(self) -> &'static str {
+        match self {
+            Statistic::PerformanceFC => "PPFC",
+            Statistic::PerformanceCurrent => "PP",
+            Statistic::Accuracy => "ACC",
+            Statistic::UnstableRate => "UR",
+            Statistic::Combo => "CB",
+            Statistic::MaxCombo => "MAXC",
+            Statistic::Hits300 => "300",
+            Statistic::Hits100 => "100",
+            Statistic::Hits50 => "50",
+            Statistic::Misses => "MISS",
+            Statistic::SliderBreaks => "SB",
+            Statistic::Score => "SCOR",
+            Statistic::Health => "HP",
+            Statistic::TimeElapsed => "TIME",
+            Statistic::TimeTotal => "LEN",
+            Statistic::StarRating => "SR",
+            Statistic::Bpm => "BPM",
+            Statistic::Mods => "MODS",
+            Statistic::GameMode => "MODE",
+            Statistic::GameState => "STAT",
+            Statistic::PbDeltaPerformance => "DPP",
+            Statistic::PbDeltaAccuracy => "DACC",
+            Statistic::SessionPlays => "PLYS",
+            Statistic::SessionPerformance => "SPP",
+            Statistic::SessionAccuracy => "SACC",
+            Statistic::SessionUnstableRate => "SUR",
+            Statistic::SessionBest => "BEST",
+            Statistic::Clock => "CLOC",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
 enum Brightness {
     Minimum,
     Medium,
     Maximum,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-enum DataProviderServer {
-    Tosu,
-    StreamCompanion,
+/// How long the core gets to close the socket and turn the display off.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Release builds on windows don't get a console, so write to the one of the
+/// shell they were started from, if any. Otherwise `--help` and the `history`
+/// output would go nowhere.
+#[cfg(all(not(debug_assertions), target_os = "windows"))]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails when started from the explorer, which has no console to attach to.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
 }
 
-impl DataProviderServer {
-    pub fn get_url(&self) -> String {
-        match self {
-            DataProviderServer::Tosu => String::from("ws://localhost:24050/ws"),
-            DataProviderServer::StreamCompanion => {
-                String::from("ws://localhost:20727/tokens?bulkUpdates=MainPipeline,LiveTokens")
-            }
-        }
+fn main() {
+    #[cfg(all(not(debug_assertions), target_os = "windows"))]
+    attach_parent_console();
+
+    let cli = Cli::parse();
+
+    let (mut config, config_error) = match Config::load() {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(e)),
+    };
+    cli.apply(&mut config);
+
+    let log_guard = logging::init(&config.log);
+
+    if let Some(e) = config_error {
+        let path = Config::path().unwrap_or_default();
+        tracing::warn!(
+            "Ignoring config {}, changes won't be saved until it can be read: {e}",
+            path.display()
+        );
     }
 
-    pub fn deserialize_response(&self, data: &[u8], old_data: OsuData) -> OsuData {
-        match self {
-            DataProviderServer::Tosu => serde_json::from_slice::<TosuResponse>(data)
-                .expect("Failed to deserialize Tosu response")
-                .to_osu_data(),
-            DataProviderServer::StreamCompanion => {
-                serde_json::from_slice::<StreamCompanionResponse>(data)
-                    .expect("Failed to deserialize StreamCompanion response")
-                    .to_osu_data(old_data)
+    match cli.command {
+        Some(Command::History {
+            limit,
+            map,
+            format,
+            output,
+        }) => {
+            if let Err(e) = cli::history(limit, map, format, output) {
+                eprintln!("{e}");
+                std::process::exit(1);
             }
+            return;
         }
+        Some(Command::Replay { ref file, speed }) => match Replay::start(file, speed) {
+            Ok(replay) => {
+                tracing::info!("Replaying {}", file.display());
+                config.provider = replay.provider.id().to_string();
+                config.connect_override = Some(replay.url);
+            }
+            Err(e) => {
+                eprintln!("Failed to replay {}: {e}", file.display());
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Run) | None => {}
     }
-}
 
-fn main() {
     let (tx1, rx1) = mpsc::channel();
     let (tx2, rx2) = mpsc::channel();
 
-    let handle = Core::run(rx1, tx2);
+    let status = Arc::new(Mutex::new(None));
+    let handle = Core::run(rx1, tx2, config.clone(), status.clone());
+
+    if cli.record {
+        tx1.send(ChannelMsg::Record(true)).expect("Channel died");
+    }
+
+    if config.api.enabled {
+        Api::spawn(&config.api, tx1.clone(), status);
+    }
 
-    App::run(tx1, rx2);
+    // Ctrl-C and SIGTERM turn the display off, like quitting from the tray.
+    let exit = tx1.clone();
+    ctrlc::set_handler(move || {
+        let _ = exit.send(ChannelMsg::AppExit);
+        force_exit_after(SHUTDOWN_TIMEOUT);
+    })
+    .expect("Failed to set the Ctrl-C handler");
 
-    handle.join().expect("Thread crashed");
+    if cli.headless {
+        run_headless(rx2);
+        handle.join().expect("Thread crashed");
+        return;
+    }
+
+    App::run(tx1, rx2, &config, log_guard);
+}
+
+/// Exits the process after `timeout`, in case the core hangs while shutting
+/// down.
+fn force_exit_after(timeout: Duration) {
+    std::thread::spawn(move || {
+        std::thread::sleep(timeout);
+        tracing::error!("Shutdown took longer than {timeout:?}, exiting anyway");
+        std::process::exit(1);
+    });
+}
+
+/// Stands in for the tray: logs what the core reports until it confirms the
+/// display is off.
+fn run_headless(rx: mpsc::Receiver<ChannelMsg>) {
+    while let Ok(msg) = rx.recv() {
+        match msg {
+            ChannelMsg::DisplayConnected(connected) => {
+                tracing::info!(connected, "Display connection changed");
+            }
+            ChannelMsg::WebsocketConnected(connected) => {
+                tracing::info!(connected, "Provider connection changed");
+            }
+            ChannelMsg::AppExit => break,
+            _ => {}
+        }
+    }
 }
@@ -0,0 +1,56 @@
+use std::fmt::Debug;
+
+use crate::{
+    config::{Config, Endpoint},
+    schema::{
+        gosumemory::Gosumemory, streamcompanion::StreamCompanion, tosu::Tosu, tosu_v2::TosuV2,
+        DeserializeError, OsuData,
+    },
+};
+
+/// A source of [`OsuData`], e.g. tosu or StreamCompanion.
+///
+/// Implementations live next to the payload types of the provider in
+/// [`crate::schema`] and are made known to the client through [`PROVIDERS`].
+pub trait DataProvider: Debug + Sync {
+    /// Stable identifier, used in the config file.
+    fn id(&self) -> &'static str;
+
+    /// Human readable name, used in the tray menu.
+    fn name(&self) -> &'static str;
+
+    /// Where the provider listens when nothing else is configured.
+    fn default_endpoint(&self) -> Endpoint;
+
+    /// URLs to try, in order, to reach the provider at `endpoint`.
+    fn urls(&self, endpoint: &Endpoint) -> Vec<String> {
+        vec![endpoint.url()]
+    }
+
+    /// Messages sent right after the websocket is connected, and again
+    /// whenever they change along with the config.
+    fn handshake(&self, _config: &Config) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Turns a payload into [`OsuData`]. Providers that only send partial
+    /// updates merge them into `old_data`.
+    fn parse(&self, data: &[u8], old_data: &OsuData) -> Result<OsuData, DeserializeError>;
+}
+
+/// Every provider the client can connect to, in tray menu order. The first
+/// one is the default.
+pub static PROVIDERS: &[&dyn DataProvider] = &[&Tosu, &TosuV2, &StreamCompanion, &Gosumemory];
+
+/// Looks up a provider by its [`DataProvider::id`].
+pub fn find(id: &str) -> Option<&'static dyn DataProvider> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|provider| provider.id() == id)
+}
+
+/// Looks up a provider by its id, falling back to the first one.
+pub fn find_or_default(id: &str) -> &'static dyn DataProvider {
+    find(id).unwrap_or(PROVIDERS[0])
+}
@@ -0,0 +1,314 @@
+use chrono::{Local, Timelike};
+use osu7_i2c::Osu7Display;
+
+use crate::{
+    bus::DisplayBus,
+    config::{CustomFormat, CustomStatistic},
+    history::Play,
+    schema::{self, GameMode, GameState, OsuData},
+    session::SessionSummary,
+    Statistic,
+};
+
+/// What a statistic looks like on the 4 digits of the display.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Readout {
+    Integer(u32),
+    /// Shown with 2 decimals.
+    Decimal(f64),
+    /// Seconds, shown as minutes and seconds.
+    Time(u32),
+    Text(String),
+    /// A signed difference, shown with up to `decimals` decimals.
+    Delta {
+        value: f64,
+        decimals: usize,
+    },
+    /// A time of day.
+    Clock {
+        hours: u32,
+        minutes: u32,
+    },
+    /// The provider doesn't report the statistic.
+    Unavailable,
+}
+
+/// Everything a readout may be computed from.
+#[derive(Debug, Clone, Copy)]
+pub struct Inputs<'a> {
+    pub data: &'a OsuData,
+    /// The best recorded play on the current map and mods.
+    pub personal_best: Option<&'a Play>,
+    pub session: &'a SessionSummary,
+}
+
+impl Readout {
+    pub fn of(statistic: Statistic, inputs: Inputs) -> Self {
+        let Inputs {
+            data,
+            personal_best,
+            session,
+        } = inputs;
+
+        let integer = |value: Option<u32>| value.map_or(Readout::Unavailable, Readout::Integer);
+        let rounded =
+            |value: Option<f64>| integer(value.map(|value| value.max(0.0).round() as u32));
+        let time = |ms: Option<f64>| {
+            ms.map_or(Readout::Unavailable, |ms| {
+                Readout::Time((ms.max(0.0) / 1000.0) as u32)
+            })
+        };
+
+        match statistic {
+            Statistic::PerformanceFC => rounded(Some(data.pp_fc())),
+            Statistic::PerformanceCurrent => rounded(Some(data.pp_current())),
+            Statistic::Accuracy => Readout::Decimal(data.accuracy()),
+            Statistic::UnstableRate => rounded(Some(data.unstable_rate())),
+            Statistic::Combo => integer(data.combo()),
+            Statistic::MaxCombo => integer(data.max_combo()),
+            Statistic::Hits300 => integer(data.hits_300()),
+            Statistic::Hits100 => integer(data.hits_100()),
+            Statistic::Hits50 => integer(data.hits_50()),
+            Statistic::Misses => integer(data.misses()),
+            Statistic::SliderBreaks => integer(data.slider_breaks()),
+            Statistic::Score => data.score().map_or(Readout::Unavailable, Self::score),
+            Statistic::Health => rounded(data.hp()),
+            Statistic::TimeElapsed => time(data.time_elapsed()),
+            Statistic::TimeTotal => time(data.time_total()),
+            Statistic::StarRating => data
+                .star_rating()
+                .map_or(Readout::Unavailable, Readout::Decimal),
+            Statistic::Bpm => rounded(data.bpm()),
+            Statistic::Mods => data.mods().map_or(Readout::Unavailable, |mods| {
+                Readout::Text(schema::mods_to_acronyms(mods))
+            }),
+            Statistic::GameMode => data.mode().map_or(Readout::Unavailable, |mode| {
+                Readout::Text(String::from(match mode {
+                    GameMode::Osu => "OSU",
+                    GameMode::Taiko => "TAIK",
+                    GameMode::Catch => "CTB",
+                    GameMode::Mania => "MANI",
+                }))
+            }),
+            Statistic::GameState => data.state().map_or(Readout::Unavailable, |state| {
+                Readout::Text(String::from(match state {
+                    GameState::Menu => "MENU",
+                    GameState::Editor => "EDIT",
+                    GameState::SongSelect => "SEL",
+                    GameState::Playing => "PLAY",
+                    GameState::Watching => "SPEC",
+                    GameState::Results => "RES",
+                    GameState::Multiplayer => "MULT",
+                }))
+            }),
+            Statistic::PbDeltaPerformance => {
+                // Compare what the play would end with, the pp so far would
+                // always start far behind the final pp of the best.
+                let pp = match data.state() {
+                    Some(GameState::Playing) => data.pp_fc(),
+                    _ => data.pp_current(),
+                };
+                personal_best.map_or(Readout::Unavailable, |best| Readout::Delta {
+                    value: pp - best.pp,
+                    decimals: 0,
+                })
+            }
+            Statistic::PbDeltaAccuracy => {
+                personal_best.map_or(Readout::Unavailable, |best| Readout::Delta {
+                    value: data.accuracy() - best.accuracy,
+                    decimals: 2,
+                })
+            }
+            Statistic::SessionPlays => Readout::Integer(session.plays),
+            Statistic::SessionPerformance => rounded(Some(session.total_pp)),
+            Statistic::SessionAccuracy => session
+                .average_accuracy
+                .map_or(Readout::Unavailable, Readout::Decimal),
+            Statistic::SessionUnstableRate => rounded(session.average_unstable_rate),
+            Statistic::SessionBest => rounded(session.best_pp),
+            Statistic::Clock => {
+                let now = Local::now();
+                Readout::Clock {
+                    hours: now.hour(),
+                    minutes: now.minute(),
+                }
+            }
+        }
+    }
+
+    pub fn of_custom(custom: &CustomStatistic, data: &OsuData) -> Self {
+        let Some(value) = custom.formula.eval(data) else {
+            return Readout::Unavailable;
+        };
+
+        match custom.format {
+            CustomFormat::Number => Readout::Delta {
+                value,
+                decimals: custom.decimals,
+            },
+            CustomFormat::Time => Readout::Time((value.max(0.0) / 1000.0) as u32),
+        }
+    }
+
+    /// Scores rarely fit 4 digits, so larger ones are shown in thousands and
+    /// from 10 million on in millions.
+    fn score(score: u64) -> Self {
+        let scaled = match score {
+            0..=9_999 => score,
+            10_000..=9_999_999 => score / 1_000,
+            _ => score / 1_000_000,
+        };
+
+        Readout::Integer(scaled.min(9999) as u32)
+    }
+
+    /// The number shown, for readouts that show one.
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            Readout::Integer(number) => Some(f64::from(*number)),
+            Readout::Decimal(number) | Readout::Delta { value: number, .. } => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// The same readout showing `value` instead, see [`Readout::value`].
+    pub fn with_value(self, value: f64) -> Self {
+        match self {
+            Readout::Integer(_) => Readout::Integer(value.max(0.0).round() as u32),
+            Readout::Decimal(_) => Readout::Decimal(value),
+            Readout::Delta { decimals, .. } => Readout::Delta { value, decimals },
+            other => other,
+        }
+    }
+
+    /// The readout as it appears on the display: 4 positions padded with
+    /// spaces, with dots and the colon inline, like ` 1:23` or `98.50`.
+    pub fn digits(&self) -> String {
+        match self {
+            Readout::Integer(number) if *number > 9999 => String::from("    "),
+            Readout::Integer(number) => format!("{number:>4}"),
+            Readout::Decimal(number) => {
+                let mut digits = 0;
+                let text: String = format!("{number:.2}")
+                    .chars()
+                    .take_while(|c| {
+                        digits += usize::from(*c != '.');
+                        digits <= 4
+                    })
+                    .collect();
+                text.trim_end_matches('.').to_string()
+            }
+            Readout::Time(seconds) => time_digits(*seconds),
+            Readout::Text(text) => text_digits(text),
+            Readout::Delta { value, decimals } => delta_digits(*value, *decimals),
+            Readout::Clock { hours, minutes } => time_digits(hours * 60 + minutes),
+            Readout::Unavailable => String::from("----"),
+        }
+    }
+
+    /// Writes the readout into the display buffer, without committing it.
+    pub fn write(&self, disp: &mut Osu7Display<DisplayBus>) {
+        match self {
+            Readout::Integer(number) => disp.write_buffer_integer(*number),
+            Readout::Decimal(number) => disp.write_buffer_float(*number as f32),
+            Readout::Time(seconds) => disp.write_buffer_time(*seconds),
+            Readout::Text(text) => disp.write_buffer_text(text),
+            Readout::Delta { value, decimals } => {
+                disp.write_buffer_signed(*value as f32, *decimals)
+            }
+            // Hours and minutes take the place of minutes and seconds.
+            Readout::Clock { hours, minutes } => disp.write_buffer_time(hours * 60 + minutes),
+            Readout::Unavailable => disp.write_buffer_text("----"),
+        }
+    }
+}
+
+/// Text the way [`Osu7Display::write_buffer_text`] shows it.
+pub fn text_digits(text: &str) -> String {
+    let shown: String = text
+        .chars()
+        .take(4)
+        .map(|c| if c.is_ascii() { c } else { ' ' })
+        .collect();
+    format!("{shown:<4}")
+}
+
+/// Mirrors [`Osu7Display::write_buffer_time`].
+fn time_digits(seconds: u32) -> String {
+    let minutes = (seconds / 60).min(99);
+    let seconds = if seconds / 60 > 99 { 59 } else { seconds % 60 };
+    format!("{minutes:>2}:{seconds:02}")
+}
+
+/// Mirrors [`Osu7Display::write_buffer_signed`].
+fn delta_digits(value: f64, decimals: usize) -> String {
+    for decimals in (0..=decimals).rev() {
+        let number = format!("{:.*}", decimals, value.abs());
+        let width = number.chars().filter(char::is_ascii_digit).count();
+        let negative = value < 0.0 && number.chars().any(|c| c.is_ascii_digit() && c != '0');
+
+        if width + usize::from(negative) > 4 {
+            continue;
+        }
+
+        let sign = if negative { "-" } else { "" };
+        return format!(
+            "{:>pad$}{sign}{number}",
+            "",
+            pad = 4 - width - usize::from(negative)
+        );
+    }
+
+    String::from("    ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        formula::Formula, provider::DataProvider, schema::streamcompanion::StreamCompanion,
+    };
+
+    #[test]
+    fn digits_match_the_display() {
+        assert_eq!(Readout::Integer(312).digits(), " 312");
+        assert_eq!(Readout::Decimal(98.5).digits(), "98.50");
+        assert_eq!(Readout::Decimal(312.456).digits(), "312.4");
+        assert_eq!(Readout::Time(83).digits(), " 1:23");
+        assert_eq!(Readout::Text(String::from("NM")).digits(), "NM  ");
+        assert_eq!(
+            Readout::Delta {
+                value: -1.256,
+                decimals: 2
+            }
+            .digits(),
+            "-1.26"
+        );
+        assert_eq!(
+            Readout::Delta {
+                value: -123.4,
+                decimals: 2
+            }
+            .digits(),
+            "-123"
+        );
+    }
+
+    #[test]
+    fn custom_times_are_milliseconds() {
+        let custom = CustomStatistic {
+            name: String::from("left"),
+            formula: Formula::parse("time_total - time_elapsed").unwrap(),
+            format: CustomFormat::Time,
+            decimals: 0,
+        };
+        let data = StreamCompanion
+            .parse(
+                br#"{"time": 10.0, "totaltime": 93000}"#,
+                &OsuData::default(),
+            )
+            .unwrap();
+
+        assert_eq!(Readout::of_custom(&custom, &data).digits(), " 1:23");
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use tungstenite::Message;
+
+use crate::provider::{self, DataProvider};
+
+const RECORDINGS_DIR: &str = "recordings";
+
+/// How often the recording is flushed to disk, which is all that is lost when
+/// the client doesn't get to finish it.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the replay server waits for client frames at a time, so it
+/// answers keepalive pings between two recorded frames.
+const REPLAY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A raw provider frame, one per line of a recording.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Entry<'a> {
+    /// Milliseconds since the recording started.
+    at_ms: u64,
+    /// The [`DataProvider::id`] of the provider that sent the frame.
+    provider: Cow<'a, str>,
+    frame: Cow<'a, str>,
+}
+
+/// Writes every provider frame with its time to a gzipped JSONL file.
+pub struct Recorder {
+    writer: GzEncoder<BufWriter<File>>,
+    started: Instant,
+    last_flush: Instant,
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn recordings_dir() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("osu7").join(RECORDINGS_DIR))
+    }
+
+    /// Starts a recording in `dir`, or [`Recorder::recordings_dir`] if there
+    /// is none, named after the current time.
+    pub fn create_default(dir: Option<&Path>) -> io::Result<Self> {
+        let dir = dir
+            .map(Path::to_path_buf)
+            .or_else(Self::recordings_dir)
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "No data directory"))?;
+        fs::create_dir_all(&dir)?;
+
+        let name = chrono::Local::now().format("recording-%Y%m%d-%H%M%S.jsonl.gz");
+        Self::create(dir.join(name.to_string()))
+    }
+
+    pub fn create(path: PathBuf) -> io::Result<Self> {
+        let file = File::create(&path)?;
+
+        Ok(Self {
+            writer: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            started: Instant::now(),
+            last_flush: Instant::now(),
+            path,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, provider: &str, frame: &[u8]) -> io::Result<()> {
+        let entry = Entry {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            provider: Cow::Borrowed(provider),
+            frame: String::from_utf8_lossy(frame),
+        };
+
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.last_flush = Instant::now();
+            self.writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the file, a recording that isn't finished misses the frames
+    /// since the last [`FLUSH_INTERVAL`].
+    pub fn finish(self) -> io::Result<PathBuf> {
+        self.writer.finish()?.flush()?;
+        Ok(self.path)
+    }
+}
+
+/// Reads the entries of a recording. One that wasn't finished ends in a
+/// broken line or gzip stream, the entries before it are still good.
+fn read_entries(path: &Path) -> io::Result<Vec<Entry<'static>>> {
+    let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+    let mut entries = Vec::new();
+
+    for line in reader.lines() {
+        let entry = line.and_then(|line| {
+            if line.trim().is_empty() {
+                return Ok(None);
+            }
+            serde_json::from_str(&line)
+                .map(Some)
+                .map_err(io::Error::from)
+        });
+
+        match entry {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => {}
+            Err(e) if entries.is_empty() => return Err(e),
+            Err(e) => {
+                tracing::warn!(
+                    "{} is cut short after {} frames: {e}",
+                    path.display(),
+                    entries.len()
+                );
+                break;
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Plays a recording back on a local websocket, so the frames go through the
+/// parser of the recorded provider like on a live connection.
+#[derive(Debug)]
+pub struct Replay {
+    pub provider: &'static dyn DataProvider,
+    pub url: String,
+}
+
+impl Replay {
+    /// Serves the recording at `path`, `speed` times as fast as it was
+    /// recorded, to every client that connects.
+    pub fn start(path: &Path, speed: f64) -> io::Result<Self> {
+        if speed.is_nan() || speed <= 0.0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Replay speed must be positive",
+            ));
+        }
+
+        let entries = read_entries(path)?;
+        let provider = entries
+            .first()
+            .and_then(|entry| provider::find(&entry.provider))
+            .ok_or_else(|| {
+                io::Error::new(ErrorKind::InvalidData, "No frames of a known provider")
+            })?;
+
+        // Frames of another provider would fail to parse or, worse, parse into
+        // something else.
+        if let Some(other) = entries.iter().find(|entry| entry.provider != provider.id()) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "The recording switches from {} to {} frames",
+                    provider.id(),
+                    other.provider
+                ),
+            ));
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("ws://{}", listener.local_addr()?);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let Ok(mut socket) = tungstenite::accept(stream) else {
+                    continue;
+                };
+
+                let _ = socket
+                    .get_ref()
+                    .set_read_timeout(Some(REPLAY_POLL_INTERVAL));
+
+                let started = Instant::now();
+                let mut connected = true;
+
+                for entry in &entries {
+                    let due = started + Duration::from_millis(entry.at_ms).div_f64(speed);
+
+                    // Reading answers pings and notices the client leaving.
+                    while connected && Instant::now() < due {
+                        connected = match socket.read() {
+                            Ok(_) => true,
+                            Err(tungstenite::Error::Io(e)) => {
+                                matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+                            }
+                            Err(_) => false,
+                        };
+                    }
+
+                    if !connected
+                        || socket
+                            .send(Message::Text(entry.frame.as_ref().into()))
+                            .is_err()
+                    {
+                        break;
+                    }
+                }
+
+                tracing::info!("Replay finished");
+
+                // Hold the last frame on the display until the client leaves.
+                loop {
+                    match socket.read() {
+                        Ok(_) => {}
+                        Err(tungstenite::Error::Io(e))
+                            if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                        Err(_) => break,
+                    }
+                }
+            }
+        });
+
+        Ok(Self { provider, url })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        readout::Readout,
+        test_support::{render, TestCore},
+        Statistic,
+    };
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("osu7-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn records_and_replays_frames() {
+        let path = temp_path("replay.jsonl.gz");
+        let mut recorder = Recorder::create(path.clone()).unwrap();
+        recorder
+            .record("streamcompanion", br#"{"acc": 97.5, "status": 2}"#)
+            .unwrap();
+        recorder
+            .record("streamcompanion", br#"{"acc": 96.25}"#)
+            .unwrap();
+        recorder.finish().unwrap();
+
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].frame, r#"{"acc": 96.25}"#);
+
+        let replay = Replay::start(&path, 10.0).unwrap();
+        assert_eq!(replay.provider.id(), "streamcompanion");
+
+        let mut config = Config {
+            provider: replay.provider.id().to_string(),
+            statistic: Statistic::Accuracy,
+            connect_override: Some(replay.url),
+            ..Config::default()
+        };
+        config.display.backend = crate::config::DisplayBackend::Mock;
+
+        let core = TestCore::start(config);
+        assert!(core.wait_for_ram(render(|disp| Readout::Decimal(96.25).write(disp))));
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn reads_unfinished_recordings() {
+        let path = temp_path("unfinished.jsonl.gz");
+        let mut recorder = Recorder::create(path.clone()).unwrap();
+        recorder.record("tosu", br#"{"a": 1}"#).unwrap();
+        recorder.record("tosu", br#"{"a": 2}"#).unwrap();
+        recorder.writer.flush().unwrap();
+        // Like a client that was killed, the gzip stream is never finished.
+        std::mem::forget(recorder);
+
+        let entries = read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn rejects_mixed_providers() {
+        let path = temp_path("mixed.jsonl.gz");
+        let mut recorder = Recorder::create(path.clone()).unwrap();
+        recorder.record("tosu", br#"{"a": 1}"#).unwrap();
+        recorder.record("streamcompanion", br#"{"a": 2}"#).unwrap();
+        recorder.finish().unwrap();
+
+        let error = Replay::start(&path, 1.0).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let _ = fs::remove_file(path);
+    }
+}
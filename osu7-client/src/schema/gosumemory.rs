@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+
+use super::{DeserializeError, GameMode, GameState, OsuData};
+use crate::{config::Endpoint, provider::DataProvider};
+
+/// `menu.state` while a map is being played.
+const STATE_PLAYING: u8 = 2;
+
+#[derive(Debug)]
+pub struct Gosumemory;
+
+impl DataProvider for Gosumemory {
+    fn id(&self) -> &'static str {
+        "gosumemory"
+    }
+
+    fn name(&self) -> &'static str {
+        "gosumemory"
+    }
+
+    fn default_endpoint(&self) -> Endpoint {
+        Endpoint::local(24050, "/ws")
+    }
+
+    fn parse(&self, data: &[u8], old_data: &OsuData) -> Result<OsuData, DeserializeError> {
+        Ok(serde_json::from_slice::<GosumemoryResponse>(data)?.into_osu_data(old_data))
+    }
+}
+
+/// The `/ws` payload of gosumemory. It has the shape tosu's v1 API was
+/// modelled after, but fills in `null` while a map is loading.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct GosumemoryResponse {
+    #[serde(default)]
+    menu: Menu,
+    #[serde(default)]
+    gameplay: Gameplay,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Menu {
+    #[serde(default)]
+    state: u8,
+    #[serde(default, rename = "gameMode")]
+    game_mode: Option<u8>,
+    #[serde(default)]
+    bm: Option<MenuBeatmap>,
+    #[serde(default)]
+    mods: Option<Mods>,
+    pp: Option<MenuPerformancePoints>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MenuBeatmap {
+    id: Option<u32>,
+    md5: Option<String>,
+    time: Option<BeatmapTime>,
+    stats: Option<BeatmapStats>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeatmapTime {
+    current: Option<f64>,
+    full: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeatmapStats {
+    #[serde(rename = "SR")]
+    star_rating: Option<f64>,
+    #[serde(rename = "BPM")]
+    bpm: Option<Bpm>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bpm {
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mods {
+    num: Option<u32>,
+}
+
+/// The pp of the selected map at a given accuracy.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MenuPerformancePoints {
+    #[serde(rename = "100")]
+    ss: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Gameplay {
+    #[serde(default)]
+    pp: PerformancePoints,
+    accuracy: Option<f64>,
+    #[serde(default)]
+    hits: Hits,
+    #[serde(default)]
+    score: Option<u64>,
+    #[serde(default)]
+    combo: Option<Combo>,
+    #[serde(default)]
+    hp: Option<Health>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hits {
+    #[serde(rename = "unstableRate")]
+    unstable_rate: Option<f64>,
+    #[serde(rename = "300")]
+    great: Option<u32>,
+    #[serde(rename = "100")]
+    ok: Option<u32>,
+    #[serde(rename = "50")]
+    meh: Option<u32>,
+    #[serde(rename = "0")]
+    miss: Option<u32>,
+    #[serde(rename = "sliderBreaks")]
+    slider_breaks: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Combo {
+    current: Option<u32>,
+    max: Option<u32>,
+}
+
+/// Health ranges from 0 to 200.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Health {
+    normal: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PerformancePoints {
+    current: Option<f64>,
+    fc: Option<f64>,
+}
+
+impl GosumemoryResponse {
+    pub fn into_osu_data(self, old_data: &OsuData) -> OsuData {
+        let menu = self.menu;
+        let gameplay = self.gameplay;
+        let bm = menu.bm.unwrap_or_default();
+        let time = bm.time.unwrap_or_default();
+        let stats = bm.stats.unwrap_or_default();
+
+        // Outside of gameplay the pp of a full combo is the SS pp of the selected map.
+        let pp_fc = if menu.state == STATE_PLAYING {
+            gameplay.pp.fc
+        } else {
+            menu.pp.and_then(|pp| pp.ss)
+        };
+
+        OsuData {
+            pp_current: gameplay.pp.current.unwrap_or(old_data.pp_current),
+            pp_fc: pp_fc.unwrap_or(old_data.pp_fc),
+            accuracy: gameplay.accuracy.unwrap_or(old_data.accuracy),
+            unstable_rate: gameplay
+                .hits
+                .unstable_rate
+                .unwrap_or(old_data.unstable_rate),
+            state: Some(GameState::from_osu(menu.state)),
+            mode: menu
+                .game_mode
+                .and_then(GameMode::from_osu)
+                .or(old_data.mode),
+            mods: menu.mods.and_then(|mods| mods.num).or(old_data.mods),
+            combo: gameplay
+                .combo
+                .and_then(|combo| combo.current)
+                .or(old_data.combo),
+            max_combo: gameplay
+                .combo
+                .and_then(|combo| combo.max)
+                .or(old_data.max_combo),
+            hits_300: gameplay.hits.great.or(old_data.hits_300),
+            hits_100: gameplay.hits.ok.or(old_data.hits_100),
+            hits_50: gameplay.hits.meh.or(old_data.hits_50),
+            misses: gameplay.hits.miss.or(old_data.misses),
+            slider_breaks: gameplay.hits.slider_breaks.or(old_data.slider_breaks),
+            score: gameplay.score.or(old_data.score),
+            hp: gameplay
+                .hp
+                .and_then(|hp| hp.normal)
+                .map(|hp| hp / 2.0)
+                .or(old_data.hp),
+            time_elapsed: time.current.or(old_data.time_elapsed),
+            time_total: time.full.or(old_data.time_total),
+            star_rating: stats.star_rating.or(old_data.star_rating),
+            bpm: stats
+                .bpm
+                .and_then(|bpm| bpm.max.or(bpm.min))
+                .or(old_data.bpm),
+            // An id of 0 and an empty checksum mean no map is loaded.
+            map_id: bm.id.filter(|id| *id != 0).or(old_data.map_id),
+            map_hash: bm
+                .md5
+                .filter(|md5| !md5.is_empty())
+                .or_else(|| old_data.map_hash.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SONG_SELECT: &str = include_str!("../../tests/fixtures/gosumemory/song_select.json");
+    const PLAYING: &str = include_str!("../../tests/fixtures/gosumemory/playing.json");
+    const MAP_LOADING: &str = include_str!("../../tests/fixtures/gosumemory/map_loading.json");
+
+    fn parse(payload: &str, old_data: &OsuData) -> OsuData {
+        Gosumemory
+            .parse(payload.as_bytes(), old_data)
+            .expect("Failed to parse fixture")
+    }
+
+    #[test]
+    fn song_select_shows_ss_pp() {
+        let data = parse(SONG_SELECT, &OsuData::default());
+
+        assert_eq!(data.pp_current(), 0.0);
+        assert_eq!(data.pp_fc(), 309.0);
+        assert_eq!(data.accuracy(), 0.0);
+        assert_eq!(data.unstable_rate(), 0.0);
+    }
+
+    #[test]
+    fn playing_reads_gameplay() {
+        let data = parse(PLAYING, &OsuData::default());
+
+        assert_eq!(data.pp_current(), 187.3);
+        assert_eq!(data.pp_fc(), 398.6);
+        assert_eq!(data.accuracy(), 97.84);
+        assert_eq!(data.unstable_rate(), 121.48);
+        assert_eq!(data.state(), Some(GameState::Playing));
+        assert_eq!(data.mods(), Some(72));
+        assert_eq!(data.hits_300(), Some(571));
+        assert_eq!(data.score(), Some(4218630));
+        assert_eq!(data.time_total(), Some(210834.0));
+        assert_eq!(data.star_rating(), Some(6.58));
+    }
+
+    #[test]
+    fn map_loading_keeps_old_values() {
+        let playing = parse(PLAYING, &OsuData::default());
+        let data = parse(MAP_LOADING, &playing);
+
+        assert_eq!(data.pp_current(), playing.pp_current());
+        assert_eq!(data.pp_fc(), playing.pp_fc());
+        assert_eq!(data.accuracy(), playing.accuracy());
+        assert_eq!(data.unstable_rate(), playing.unstable_rate());
+        assert_eq!(data.star_rating(), playing.star_rating());
+    }
+
+    #[test]
+    fn mismatched_payload_is_a_schema_error() {
+        let result = Gosumemory.parse(b"{\"gameplay\": []}", &OsuData::default());
+
+        assert!(matches!(result, Err(DeserializeError::Schema(_))));
+    }
+}
@@ -1,13 +1,270 @@
+use std::{collections::BTreeMap, fmt};
+
 use getset::CopyGetters;
+use serde::Serialize;
+use serde_json::error::Category;
 
+pub mod gosumemory;
 pub mod streamcompanion;
 pub mod tosu;
+pub mod tosu_v2;
 
-#[derive(Default, Debug, Clone, Copy, CopyGetters)]
+/// Everything the client knows about the game, normalized across providers.
+///
+/// Fields that are `None` are not reported by the current provider.
+#[derive(Default, Debug, Clone, CopyGetters, Serialize)]
 #[get_copy = "pub"]
 pub struct OsuData {
     pp_current: f64,
     pp_fc: f64,
     accuracy: f64,
     unstable_rate: f64,
+    state: Option<GameState>,
+    mode: Option<GameMode>,
+    /// Bitflags as used by osu!.
+    mods: Option<u32>,
+    combo: Option<u32>,
+    max_combo: Option<u32>,
+    hits_300: Option<u32>,
+    hits_100: Option<u32>,
+    hits_50: Option<u32>,
+    misses: Option<u32>,
+    slider_breaks: Option<u32>,
+    score: Option<u64>,
+    /// Health in percent.
+    hp: Option<f64>,
+    /// Position in the map in milliseconds.
+    time_elapsed: Option<f64>,
+    /// Length of the map in milliseconds.
+    time_total: Option<f64>,
+    star_rating: Option<f64>,
+    bpm: Option<f64>,
+    /// Beatmap id on the osu! website.
+    map_id: Option<u32>,
+    /// MD5 checksum of the `.osu` file.
+    #[getset(skip)]
+    map_hash: Option<String>,
+    /// Values the provider sent that have no field above, by token name.
+    #[getset(skip)]
+    raw: BTreeMap<String, serde_json::Value>,
+}
+
+impl OsuData {
+    pub fn map_hash(&self) -> Option<&str> {
+        self.map_hash.as_deref()
+    }
+
+    pub fn raw(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.raw
+    }
+}
+
+/// What the game is currently doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameState {
+    Menu,
+    Editor,
+    SongSelect,
+    Playing,
+    /// Watching a replay or spectating, which isn't a play of the user's.
+    Watching,
+    Results,
+    Multiplayer,
+}
+
+impl GameState {
+    /// Maps the state number osu! stable uses internally.
+    pub fn from_osu(number: u8) -> Self {
+        match number {
+            1 | 4 => GameState::Editor,
+            2 => GameState::Playing,
+            5 => GameState::SongSelect,
+            7 | 14 | 17 | 18 => GameState::Results,
+            11..=13 => GameState::Multiplayer,
+            _ => GameState::Menu,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    Osu,
+    Taiko,
+    Catch,
+    Mania,
+}
+
+impl GameMode {
+    pub fn from_osu(number: u8) -> Option<Self> {
+        match number {
+            0 => Some(GameMode::Osu),
+            1 => Some(GameMode::Taiko),
+            2 => Some(GameMode::Catch),
+            3 => Some(GameMode::Mania),
+            _ => None,
+        }
+    }
+}
+
+/// Mod acronyms by their bit, in the order osu! lists them.
+const MODS: &[(&str, u32)] = &[
+    ("NF", 1 << 0),
+    ("EZ", 1 << 1),
+    ("TD", 1 << 2),
+    ("HD", 1 << 3),
+    ("HR", 1 << 4),
+    ("SD", 1 << 5),
+    ("DT", 1 << 6),
+    ("RX", 1 << 7),
+    ("HT", 1 << 8),
+    ("NC", 1 << 9),
+    ("FL", 1 << 10),
+    ("AT", 1 << 11),
+    ("SO", 1 << 12),
+    ("AP", 1 << 13),
+    ("PF", 1 << 14),
+    ("4K", 1 << 15),
+    ("5K", 1 << 16),
+    ("6K", 1 << 17),
+    ("7K", 1 << 18),
+    ("8K", 1 << 19),
+    ("FI", 1 << 20),
+    ("RD", 1 << 21),
+    ("CN", 1 << 22),
+    ("TP", 1 << 23),
+    ("9K", 1 << 24),
+    ("CO", 1 << 25),
+    ("1K", 1 << 26),
+    ("3K", 1 << 27),
+    ("2K", 1 << 28),
+    ("V2", 1 << 29),
+    ("MR", 1 << 30),
+];
+
+/// Formats mod bitflags the way osu! shows them, e.g. `HDDT`. Mods implied by
+/// another one (DT by NC, SD by PF) are left out.
+pub fn mods_to_acronyms(mods: u32) -> String {
+    let mut implied = 0;
+    if mods & (1 << 9) != 0 {
+        implied |= 1 << 6;
+    }
+    if mods & (1 << 14) != 0 {
+        implied |= 1 << 5;
+    }
+
+    let acronyms: String = MODS
+        .iter()
+        .filter(|(_, bit)| mods & bit != 0 && implied & bit == 0)
+        .map(|(acronym, _)| *acronym)
+        .collect();
+
+    if acronyms.is_empty() {
+        String::from("NM")
+    } else {
+        acronyms
+    }
+}
+
+/// Parses mod acronyms in any of the usual notations (`HDDT`, `HD,DT`,
+/// `None`) into bitflags. Unknown acronyms are ignored.
+pub fn mods_from_acronyms(acronyms: &str) -> u32 {
+    let letters: Vec<char> = acronyms
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    let mut mods = 0;
+    for pair in letters.chunks_exact(2) {
+        let acronym: String = pair.iter().collect();
+        if let Some((_, bit)) = MODS.iter().find(|(a, _)| *a == acronym) {
+            mods |= bit;
+        }
+    }
+
+    // NC and PF always come with the mod they extend.
+    if mods & (1 << 9) != 0 {
+        mods |= 1 << 6;
+    }
+    if mods & (1 << 14) != 0 {
+        mods |= 1 << 5;
+    }
+
+    mods
+}
+
+/// A provider payload that could not be turned into [`OsuData`].
+#[derive(Debug)]
+pub enum DeserializeError {
+    /// The payload is not valid JSON.
+    Syntax(serde_json::Error),
+    /// The payload is valid JSON, but doesn't match the provider's schema.
+    Schema(serde_json::Error),
+}
+
+impl From<serde_json::Error> for DeserializeError {
+    fn from(e: serde_json::Error) -> Self {
+        match e.classify() {
+            Category::Data => DeserializeError::Schema(e),
+            Category::Syntax | Category::Eof | Category::Io => DeserializeError::Syntax(e),
+        }
+    }
+}
+
+impl DeserializeError {
+    /// The error without the line and column, which differ between payloads
+    /// with the same problem.
+    pub fn without_position(&self) -> String {
+        let message = self.to_string();
+        match message.rfind(" at line ") {
+            Some(end) => message[..end].to_string(),
+            None => message,
+        }
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeserializeError::Syntax(e) => write!(f, "invalid JSON: {e}"),
+            DeserializeError::Schema(e) => write!(f, "unexpected payload: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeserializeError::Syntax(e) | DeserializeError::Schema(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mods_round_trip() {
+        assert_eq!(mods_from_acronyms("HD,DT"), 72);
+        assert_eq!(mods_from_acronyms("None"), 0);
+        assert_eq!(mods_to_acronyms(72), "HDDT");
+        assert_eq!(mods_to_acronyms(mods_from_acronyms("HDNC")), "HDNC");
+        assert_eq!(mods_to_acronyms(0), "NM");
+    }
+
+    #[test]
+    fn errors_compare_without_position() {
+        let error = |payload: &str| {
+            DeserializeError::from(serde_json::from_str::<u32>(payload).unwrap_err())
+        };
+
+        assert_ne!(error(r#""a""#).to_string(), error(r#"   "a""#).to_string());
+        assert_eq!(
+            error(r#""a""#).without_position(),
+            error(r#"   "a""#).without_position()
+        );
+    }
 }
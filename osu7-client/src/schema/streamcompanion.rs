@@ -1,8 +1,132 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{mods_from_acronyms, DeserializeError, GameMode, GameState, OsuData};
+use crate::{
+    config::{Config, Endpoint},
+    provider::DataProvider,
+    Statistic,
+};
+
+#[derive(Debug)]
+pub struct StreamCompanion;
+
+impl DataProvider for StreamCompanion {
+    fn id(&self) -> &'static str {
+        "streamcompanion"
+    }
 
-use super::OsuData;
+    fn name(&self) -> &'static str {
+        "StreamCompanion"
+    }
+
+    fn default_endpoint(&self) -> Endpoint {
+        Endpoint::local(20727, "/tokens?bulkUpdates=MainPipeline,LiveTokens")
+    }
 
-#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+    /// StreamCompanion only sends the tokens we subscribe to, so ask for the
+    /// ones the configured statistics and the play history need plus the
+    /// configured extra tokens. Every token asked for is sent on every update,
+    /// so the others are left out until a statistic that needs them is shown.
+    fn handshake(&self, config: &Config) -> Vec<String> {
+        let mut tokens: Vec<&str> = config
+            .statistics_in_use()
+            .into_iter()
+            .filter_map(token)
+            .collect();
+        tokens.extend(HISTORY_TOKENS);
+        tokens.extend(config.raw_tokens_in_use());
+        tokens.sort_unstable();
+        tokens.dedup();
+
+        vec![serde_json::to_string(&tokens).expect("Failed to serialize token list")]
+    }
+
+    fn parse(&self, data: &[u8], old_data: &OsuData) -> Result<OsuData, DeserializeError> {
+        Ok(serde_json::from_slice::<StreamCompanionResponse>(data)?.into_osu_data(old_data))
+    }
+}
+
+/// What a finished play is recorded with, see [`crate::history::Play`].
+const HISTORY_TOKENS: &[&str] = &[
+    "status",
+    "mapid",
+    "md5",
+    "mods",
+    "ppIfMapEndsNow",
+    "acc",
+    "unstableRate",
+    "miss",
+    "currentMaxCombo",
+    "score",
+];
+
+/// The StreamCompanion token a statistic is read from.
+fn token(statistic: Statistic) -> Option<&'static str> {
+    match statistic {
+        Statistic::PerformanceFC => Some("ppIfRestFced"),
+        Statistic::PerformanceCurrent => Some("ppIfMapEndsNow"),
+        Statistic::Accuracy => Some("acc"),
+        Statistic::UnstableRate => Some("unstableRate"),
+        Statistic::Combo => Some("combo"),
+        Statistic::MaxCombo => Some("currentMaxCombo"),
+        Statistic::Hits300 => Some("c300"),
+        Statistic::Hits100 => Some("c100"),
+        Statistic::Hits50 => Some("c50"),
+        Statistic::Misses => Some("miss"),
+        Statistic::SliderBreaks => Some("sliderBreaks"),
+        Statistic::Score => Some("score"),
+        Statistic::Health => Some("playerHp"),
+        Statistic::TimeElapsed => Some("time"),
+        Statistic::TimeTotal => Some("totaltime"),
+        Statistic::StarRating => Some("mStars"),
+        Statistic::Bpm => Some("mMainBpm"),
+        Statistic::Mods => Some("mods"),
+        Statistic::GameMode => Some("gameMode"),
+        Statistic::GameState => Some("status"),
+        Statistic::PbDeltaPerformance => Some("ppIfRestFced"),
+        Statistic::PbDeltaAccuracy => Some("acc"),
+        // Computed from the plays, see `HISTORY_TOKENS`.
+        Statistic::SessionPlays
+        | Statistic::SessionPerformance
+        | Statistic::SessionAccuracy
+        | Statistic::SessionUnstableRate
+        | Statistic::SessionBest
+        | Statistic::Clock => None,
+    }
+}
+
+/// Maps the `status` token, a set of flags of which only one is set at a time.
+fn game_state(status: u32) -> GameState {
+    match status {
+        1 => GameState::SongSelect,
+        2 => GameState::Playing,
+        8 => GameState::Watching,
+        16 => GameState::Editor,
+        32 => GameState::Results,
+        _ => GameState::Menu,
+    }
+}
+
+/// The `gameMode` token is either the number or the name of the mode.
+fn game_mode(value: &Value) -> Option<GameMode> {
+    match value {
+        Value::Number(number) => GameMode::from_osu(number.as_u64()?.try_into().ok()?),
+        Value::String(name) => match name.as_str() {
+            "Osu" => Some(GameMode::Osu),
+            "Taiko" => Some(GameMode::Taiko),
+            "CatchTheBeat" => Some(GameMode::Catch),
+            "OsuMania" => Some(GameMode::Mania),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A (bulk) update of tokens. Only tokens that changed are included.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct StreamCompanionResponse {
     #[serde(rename = "ppIfMapEndsNow")]
     pp_ends_now: Option<f64>,
@@ -12,15 +136,127 @@ pub struct StreamCompanionResponse {
     accuracy: Option<f64>,
     #[serde(rename = "unstableRate")]
     unstable_rate: Option<f64>,
+    combo: Option<f64>,
+    #[serde(rename = "currentMaxCombo")]
+    max_combo: Option<f64>,
+    c300: Option<f64>,
+    c100: Option<f64>,
+    c50: Option<f64>,
+    miss: Option<f64>,
+    #[serde(rename = "sliderBreaks")]
+    slider_breaks: Option<f64>,
+    score: Option<f64>,
+    /// Ranges from 0 to 200.
+    #[serde(rename = "playerHp")]
+    hp: Option<f64>,
+    /// Seconds into the map.
+    time: Option<f64>,
+    /// Milliseconds.
+    #[serde(rename = "totaltime")]
+    total_time: Option<f64>,
+    #[serde(rename = "mStars")]
+    star_rating: Option<f64>,
+    #[serde(rename = "mMainBpm")]
+    bpm: Option<f64>,
+    /// Comma separated acronyms, e.g. `HD,DT`, or `None`.
+    mods: Option<String>,
+    #[serde(rename = "gameMode")]
+    game_mode: Option<Value>,
+    status: Option<u32>,
+    #[serde(rename = "mapid")]
+    map_id: Option<f64>,
+    md5: Option<String>,
+    /// Every other token, by name.
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+/// StreamCompanion sends every number as a double.
+fn count(value: Option<f64>) -> Option<u32> {
+    value.map(|value| value as u32)
 }
 
 impl StreamCompanionResponse {
-    pub fn to_osu_data(self, old_data: OsuData) -> OsuData {
+    pub fn into_osu_data(self, old_data: &OsuData) -> OsuData {
+        let mut raw = old_data.raw().clone();
+        raw.extend(self.other);
+
         OsuData {
             pp_current: self.pp_ends_now.unwrap_or(old_data.pp_current),
             pp_fc: self.pp_if_fc.unwrap_or(old_data.pp_fc),
             accuracy: self.accuracy.unwrap_or(old_data.accuracy),
             unstable_rate: self.unstable_rate.unwrap_or(old_data.unstable_rate),
+            state: self.status.map(game_state).or(old_data.state),
+            mode: self
+                .game_mode
+                .as_ref()
+                .and_then(game_mode)
+                .or(old_data.mode),
+            mods: self
+                .mods
+                .as_deref()
+                .map(mods_from_acronyms)
+                .or(old_data.mods),
+            combo: count(self.combo).or(old_data.combo),
+            max_combo: count(self.max_combo).or(old_data.max_combo),
+            hits_300: count(self.c300).or(old_data.hits_300),
+            hits_100: count(self.c100).or(old_data.hits_100),
+            hits_50: count(self.c50).or(old_data.hits_50),
+            misses: count(self.miss).or(old_data.misses),
+            slider_breaks: count(self.slider_breaks).or(old_data.slider_breaks),
+            score: self.score.map(|score| score as u64).or(old_data.score),
+            hp: self.hp.map(|hp| hp / 2.0).or(old_data.hp),
+            time_elapsed: self
+                .time
+                .map(|secs| secs * 1000.0)
+                .or(old_data.time_elapsed),
+            time_total: self.total_time.or(old_data.time_total),
+            star_rating: self.star_rating.or(old_data.star_rating),
+            bpm: self.bpm.or(old_data.bpm),
+            map_id: count(self.map_id).or(old_data.map_id),
+            map_hash: self.md5.or_else(|| old_data.map_hash.clone()),
+            raw,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_updates_are_merged() {
+        let first = br#"{"ppIfMapEndsNow": 120.5, "acc": 98.5, "mapStrains": {"0": 1.5}}"#;
+        let second = br#"{"acc": 97.25, "time": 12.5, "mods": "HD,DT", "status": 2}"#;
+
+        let data = StreamCompanion.parse(first, &OsuData::default()).unwrap();
+        let data = StreamCompanion.parse(second, &data).unwrap();
+
+        assert_eq!(data.pp_current(), 120.5);
+        assert_eq!(data.accuracy(), 97.25);
+        assert_eq!(data.time_elapsed(), Some(12500.0));
+        assert_eq!(data.mods(), Some(72));
+        assert_eq!(data.state(), Some(GameState::Playing));
+        assert_eq!(data.combo(), None);
+        assert!(data.raw().contains_key("mapStrains"));
+    }
+
+    #[test]
+    fn subscribes_to_configured_tokens() {
+        let config = Config {
+            statistic: Statistic::Accuracy,
+            extra_tokens: vec![String::from("mapStrains"), String::from("acc")],
+            ..Config::default()
+        };
+
+        let handshake = StreamCompanion.handshake(&config);
+        let tokens: Vec<String> = serde_json::from_str(&handshake[0]).unwrap();
+
+        assert_eq!(tokens.iter().filter(|token| *token == "acc").count(), 1);
+        assert!(tokens.iter().any(|token| token == "mapStrains"));
+        assert!(tokens.iter().any(|token| token == "md5"));
+        // Other statistics are subscribed to once they are shown.
+        assert!(!tokens.iter().any(|token| token == "c300"));
+        assert!(tokens.is_sorted());
+    }
+}
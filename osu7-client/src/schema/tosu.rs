@@ -1,23 +1,125 @@
 use serde::{Deserialize, Serialize};
 
-use super::OsuData;
+use super::{DeserializeError, GameMode, GameState, OsuData};
+use crate::{config::Endpoint, provider::DataProvider};
 
-#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct Tosu;
+
+impl DataProvider for Tosu {
+    fn id(&self) -> &'static str {
+        "tosu"
+    }
+
+    fn name(&self) -> &'static str {
+        "Tosu"
+    }
+
+    fn default_endpoint(&self) -> Endpoint {
+        Endpoint::local(24050, "/ws")
+    }
+
+    fn parse(&self, data: &[u8], _old_data: &OsuData) -> Result<OsuData, DeserializeError> {
+        Ok(serde_json::from_slice::<TosuResponse>(data)?.into_osu_data())
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct TosuResponse {
+    #[serde(default)]
+    menu: Menu,
     gameplay: Gameplay,
 }
 
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Menu {
+    state: Option<u8>,
+    #[serde(rename = "gameMode")]
+    game_mode: Option<u8>,
+    bm: MenuBeatmap,
+    mods: Option<Mods>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MenuBeatmap {
+    id: Option<u32>,
+    md5: Option<String>,
+    time: Option<BeatmapTime>,
+    stats: Option<BeatmapStats>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeatmapTime {
+    current: Option<f64>,
+    full: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeatmapStats {
+    #[serde(rename = "SR")]
+    star_rating: Option<f64>,
+    #[serde(rename = "BPM")]
+    bpm: Option<Bpm>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bpm {
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mods {
+    num: Option<u32>,
+}
+
 #[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Gameplay {
     pp: PerformancePoints,
     accuracy: f64,
     hits: Hits,
+    #[serde(default)]
+    score: Option<u64>,
+    #[serde(default)]
+    combo: Option<Combo>,
+    #[serde(default)]
+    hp: Option<Health>,
 }
 
 #[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Hits {
     #[serde(rename = "unstableRate")]
     unstable_rate: f64,
+    #[serde(default, rename = "300")]
+    great: Option<u32>,
+    #[serde(default, rename = "100")]
+    ok: Option<u32>,
+    #[serde(default, rename = "50")]
+    meh: Option<u32>,
+    #[serde(default, rename = "0")]
+    miss: Option<u32>,
+    #[serde(default, rename = "sliderBreaks")]
+    slider_breaks: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Combo {
+    current: Option<u32>,
+    max: Option<u32>,
+}
+
+/// Health ranges from 0 to 200.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Health {
+    normal: Option<f64>,
 }
 
 #[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
@@ -27,12 +129,35 @@ pub struct PerformancePoints {
 }
 
 impl TosuResponse {
-    pub fn to_osu_data(self) -> OsuData {
+    pub fn into_osu_data(self) -> OsuData {
+        let menu = self.menu;
+        let gameplay = self.gameplay;
+        let stats = menu.bm.stats.unwrap_or_default();
+
         OsuData {
-            pp_current: self.gameplay.pp.current,
-            pp_fc: self.gameplay.pp.fc,
-            accuracy: self.gameplay.accuracy,
-            unstable_rate: self.gameplay.hits.unstable_rate,
+            pp_current: gameplay.pp.current,
+            pp_fc: gameplay.pp.fc,
+            accuracy: gameplay.accuracy,
+            unstable_rate: gameplay.hits.unstable_rate,
+            state: menu.state.map(GameState::from_osu),
+            mode: menu.game_mode.and_then(GameMode::from_osu),
+            mods: menu.mods.and_then(|mods| mods.num),
+            combo: gameplay.combo.and_then(|combo| combo.current),
+            max_combo: gameplay.combo.and_then(|combo| combo.max),
+            hits_300: gameplay.hits.great,
+            hits_100: gameplay.hits.ok,
+            hits_50: gameplay.hits.meh,
+            misses: gameplay.hits.miss,
+            slider_breaks: gameplay.hits.slider_breaks,
+            score: gameplay.score,
+            hp: gameplay.hp.and_then(|hp| hp.normal).map(|hp| hp / 2.0),
+            time_elapsed: menu.bm.time.and_then(|time| time.current),
+            time_total: menu.bm.time.and_then(|time| time.full),
+            star_rating: stats.star_rating,
+            bpm: stats.bpm.and_then(|bpm| bpm.max.or(bpm.min)),
+            map_id: menu.bm.id,
+            map_hash: menu.bm.md5.filter(|md5| !md5.is_empty()),
+            ..Default::default()
         }
     }
 }
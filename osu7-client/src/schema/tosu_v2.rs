@@ -0,0 +1,324 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{tosu::TosuResponse, DeserializeError, GameMode, GameState, OsuData};
+use crate::{config::Endpoint, provider::DataProvider};
+
+const V2_PATH: &str = "/websocket/v2";
+const V1_PATH: &str = "/ws";
+
+/// tosu through its `/websocket/v2` API, falling back to the v1 `/ws` API of
+/// older tosu versions.
+#[derive(Debug)]
+pub struct TosuV2;
+
+impl DataProvider for TosuV2 {
+    fn id(&self) -> &'static str {
+        "tosu_v2"
+    }
+
+    fn name(&self) -> &'static str {
+        "Tosu (v2)"
+    }
+
+    fn default_endpoint(&self) -> Endpoint {
+        Endpoint::local(24050, V2_PATH)
+    }
+
+    fn urls(&self, endpoint: &Endpoint) -> Vec<String> {
+        let mut urls = vec![endpoint.url()];
+
+        if endpoint.url.is_none() && endpoint.path == V2_PATH {
+            let v1 = Endpoint {
+                path: V1_PATH.to_string(),
+                ..endpoint.clone()
+            };
+            urls.push(v1.url());
+        }
+
+        urls
+    }
+
+    /// Depending on the endpoint we ended up on, tosu speaks either API.
+    fn parse(&self, data: &[u8], _old_data: &OsuData) -> Result<OsuData, DeserializeError> {
+        let payload: Value = serde_json::from_slice(data)?;
+
+        // Decide up front, so errors come from the API the payload is in.
+        if payload.get("menu").is_some() {
+            Ok(TosuResponse::deserialize(payload)?.into_osu_data())
+        } else {
+            Ok(TosuV2Response::deserialize(payload)?.to_osu_data())
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TosuV2Response {
+    state: Numbered,
+    beatmap: Beatmap,
+    play: Play,
+    #[serde(default)]
+    results_screen: Option<ResultsScreen>,
+}
+
+/// tosu v2 represents enums as their number and name.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Numbered {
+    number: u8,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Beatmap {
+    id: Option<u32>,
+    checksum: Option<String>,
+    time: BeatmapTime,
+    mode: Option<Numbered>,
+    stats: BeatmapStats,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BeatmapTime {
+    live: Option<f64>,
+    last_object: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BeatmapStats {
+    stars: Option<Stars>,
+    bpm: Option<Bpm>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stars {
+    total: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bpm {
+    realtime: Option<f64>,
+    common: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Play {
+    mode: Option<Numbered>,
+    score: Option<u64>,
+    accuracy: Option<f64>,
+    health_bar: Option<HealthBar>,
+    hits: Hits,
+    combo: Option<Combo>,
+    mods: Option<Mods>,
+    pp: PerformancePoints,
+    unstable_rate: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthBar {
+    normal: Option<f64>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hits {
+    #[serde(rename = "300")]
+    great: Option<u32>,
+    #[serde(rename = "100")]
+    ok: Option<u32>,
+    #[serde(rename = "50")]
+    meh: Option<u32>,
+    #[serde(rename = "0")]
+    miss: Option<u32>,
+    #[serde(rename = "sliderBreaks")]
+    slider_breaks: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Combo {
+    current: Option<u32>,
+    max: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mods {
+    number: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerformancePoints {
+    current: Option<f64>,
+    fc: Option<f64>,
+}
+
+/// The play shown on the results screen.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ResultsScreen {
+    mode: Option<Numbered>,
+    score: Option<u64>,
+    accuracy: Option<f64>,
+    max_combo: Option<u32>,
+    hits: Hits,
+    mods: Option<Mods>,
+    pp: PerformancePoints,
+}
+
+impl TosuV2Response {
+    pub fn to_osu_data(&self) -> OsuData {
+        let state = GameState::from_osu(self.state.number);
+        let play = &self.play;
+        let beatmap = &self.beatmap;
+
+        let mut data = OsuData {
+            pp_current: play.pp.current.unwrap_or_default(),
+            pp_fc: play.pp.fc.unwrap_or_default(),
+            accuracy: play.accuracy.unwrap_or_default(),
+            unstable_rate: play.unstable_rate.unwrap_or_default(),
+            state: Some(state),
+            mode: play
+                .mode
+                .or(beatmap.mode)
+                .and_then(|mode| GameMode::from_osu(mode.number)),
+            mods: play.mods.and_then(|mods| mods.number),
+            combo: play.combo.and_then(|combo| combo.current),
+            max_combo: play.combo.and_then(|combo| combo.max),
+            hits_300: play.hits.great,
+            hits_100: play.hits.ok,
+            hits_50: play.hits.meh,
+            misses: play.hits.miss,
+            slider_breaks: play.hits.slider_breaks,
+            score: play.score,
+            hp: play.health_bar.and_then(|hp| hp.normal),
+            time_elapsed: beatmap.time.live,
+            time_total: beatmap.time.last_object,
+            star_rating: beatmap.stats.stars.and_then(|stars| stars.total),
+            bpm: beatmap
+                .stats
+                .bpm
+                .and_then(|bpm| bpm.common.or(bpm.realtime)),
+            map_id: beatmap.id,
+            map_hash: beatmap.checksum.clone().filter(|md5| !md5.is_empty()),
+            ..Default::default()
+        };
+
+        // The play object is reset once the results screen shows up.
+        if let (GameState::Results, Some(results)) = (state, &self.results_screen) {
+            data.pp_current = results.pp.current.unwrap_or(data.pp_current);
+            data.pp_fc = results.pp.fc.unwrap_or(data.pp_fc);
+            data.accuracy = results.accuracy.unwrap_or(data.accuracy);
+            data.mode = results
+                .mode
+                .and_then(|mode| GameMode::from_osu(mode.number))
+                .or(data.mode);
+            data.mods = results.mods.and_then(|mods| mods.number).or(data.mods);
+            data.combo = None;
+            data.max_combo = results.max_combo.or(data.max_combo);
+            data.hits_300 = results.hits.great;
+            data.hits_100 = results.hits.ok;
+            data.hits_50 = results.hits.meh;
+            data.misses = results.hits.miss;
+            data.slider_breaks = results.hits.slider_breaks;
+            data.score = results.score.or(data.score);
+            data.hp = None;
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYING: &str = include_str!("../../tests/fixtures/tosu_v2/playing.json");
+    const RESULTS: &str = include_str!("../../tests/fixtures/tosu_v2/results.json");
+    const V1: &str = include_str!("../../tests/fixtures/gosumemory/playing.json");
+
+    fn parse(payload: &str) -> OsuData {
+        TosuV2
+            .parse(payload.as_bytes(), &OsuData::default())
+            .expect("Failed to parse fixture")
+    }
+
+    #[test]
+    fn playing_reads_play_and_beatmap() {
+        let data = parse(PLAYING);
+
+        assert_eq!(data.state(), Some(GameState::Playing));
+        assert_eq!(data.mode(), Some(GameMode::Osu));
+        assert_eq!(data.pp_current(), 187.3);
+        assert_eq!(data.pp_fc(), 398.6);
+        assert_eq!(data.accuracy(), 97.84);
+        assert_eq!(data.unstable_rate(), 121.48);
+        assert_eq!(data.mods(), Some(72));
+        assert_eq!(data.combo(), Some(412));
+        assert_eq!(data.hits_100(), Some(17));
+        assert_eq!(data.misses(), Some(0));
+        assert_eq!(data.score(), Some(4218630));
+        assert_eq!(data.hp(), Some(82.1));
+        assert_eq!(data.time_elapsed(), Some(96532.0));
+        assert_eq!(data.time_total(), Some(210834.0));
+        assert_eq!(data.star_rating(), Some(6.58));
+        assert_eq!(data.bpm(), Some(195.0));
+        assert_eq!(data.map_id(), Some(1860433));
+        assert_eq!(data.map_hash(), Some("aa6fc9f1b5d0df12be2b1e0e1cb0ab48"));
+    }
+
+    #[test]
+    fn results_screen_shows_final_play() {
+        let data = parse(RESULTS);
+
+        assert_eq!(data.state(), Some(GameState::Results));
+        assert_eq!(data.pp_current(), 372.9);
+        assert_eq!(data.accuracy(), 97.21);
+        assert_eq!(data.max_combo(), Some(1398));
+        assert_eq!(data.misses(), Some(2));
+        assert_eq!(data.slider_breaks(), Some(1));
+        assert_eq!(data.combo(), None);
+    }
+
+    #[test]
+    fn v1_payload_falls_back() {
+        let data = parse(V1);
+
+        assert_eq!(data.pp_current(), 187.3);
+        assert_eq!(data.unstable_rate(), 121.48);
+        assert_eq!(data.state(), Some(GameState::Playing));
+        assert_eq!(data.combo(), Some(412));
+        assert_eq!(data.hp(), Some(82.1));
+        assert_eq!(data.bpm(), Some(130.0));
+    }
+
+    #[test]
+    fn reports_field_errors() {
+        let payload = PLAYING.replacen("\"accuracy\": 97.84", "\"accuracy\": \"high\"", 1);
+        let error = TosuV2
+            .parse(payload.as_bytes(), &OsuData::default())
+            .unwrap_err();
+
+        assert!(error.to_string().contains("invalid type: string \"high\""));
+    }
+
+    #[test]
+    fn falls_back_to_v1_endpoint() {
+        let urls = TosuV2.urls(&TosuV2.default_endpoint());
+
+        assert_eq!(
+            urls,
+            [
+                "ws://localhost:24050/websocket/v2",
+                "ws://localhost:24050/ws"
+            ]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::history::Play;
+
+const REPORTS_DIR: &str = "sessions";
+
+/// The plays finished since the client was started.
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    /// Seconds since the unix epoch.
+    started: i64,
+    plays: Vec<Play>,
+}
+
+/// Aggregates over the plays of a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct SessionSummary {
+    pub plays: u32,
+    pub total_pp: f64,
+    pub average_accuracy: Option<f64>,
+    pub average_unstable_rate: Option<f64>,
+    /// pp of the best play.
+    pub best_pp: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    #[serde(flatten)]
+    session: &'a Session,
+    ended: i64,
+    summary: SessionSummary,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            started: now(),
+            plays: Vec::new(),
+        }
+    }
+}
+
+impl Session {
+    pub fn reports_dir() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("osu7").join(REPORTS_DIR))
+    }
+
+    pub fn record(&mut self, play: Play) {
+        self.plays.push(play);
+    }
+
+    pub fn summary(&self) -> SessionSummary {
+        let count = self.plays.len();
+        let average = |value: fn(&Play) -> f64| {
+            (count > 0).then(|| self.plays.iter().map(value).sum::<f64>() / count as f64)
+        };
+
+        SessionSummary {
+            plays: count as u32,
+            total_pp: self.plays.iter().map(|play| play.pp).sum(),
+            average_accuracy: average(|play| play.accuracy),
+            average_unstable_rate: average(|play| play.unstable_rate),
+            best_pp: self.plays.iter().map(|play| play.pp).reduce(f64::max),
+        }
+    }
+
+    /// Writes the session with its summary as JSON into
+    /// [`Session::reports_dir`]. Sessions without plays are skipped.
+    pub fn save_report(&self) -> io::Result<Option<PathBuf>> {
+        let Some(dir) = Self::reports_dir() else {
+            return Ok(None);
+        };
+
+        if self.plays.is_empty() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&dir)?;
+
+        let report = Report {
+            session: self,
+            ended: now(),
+            summary: self.summary(),
+        };
+        let path = dir.join(format!("session-{}.json", self.started));
+        let contents = serde_json::to_string_pretty(&report).map_err(io::Error::other)?;
+        fs::write(&path, contents)?;
+
+        Ok(Some(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(pp: f64, accuracy: f64, unstable_rate: f64) -> Play {
+        Play {
+            timestamp: 0,
+            map_id: None,
+            map_hash: None,
+            mods: None,
+            pp,
+            accuracy,
+            unstable_rate,
+            misses: None,
+            max_combo: None,
+            score: None,
+        }
+    }
+
+    #[test]
+    fn summarizes_plays() {
+        let mut session = Session::default();
+        assert_eq!(session.summary(), SessionSummary::default());
+
+        session.record(play(200.0, 98.0, 110.0));
+        session.record(play(300.0, 96.0, 130.0));
+
+        assert_eq!(
+            session.summary(),
+            SessionSummary {
+                plays: 2,
+                total_pp: 500.0,
+                average_accuracy: Some(97.0),
+                average_unstable_rate: Some(120.0),
+                best_pp: Some(300.0),
+            }
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Stand-ins for the outside world in end-to-end tests: websocket servers
+//! that imitate the providers, and a [`Core`] writing to a [`MockBus`].
+
+use std::{
+    net::TcpListener,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use osu7_i2c::Osu7Display;
+use tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message,
+};
+
+use crate::{
+    bus::{DisplayBus, MockBus},
+    config::{Config, DisplayBackend, Endpoint},
+    core::Core,
+    history::History,
+    provider, ChannelMsg,
+};
+
+/// How long to wait for the core before giving up.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One step of what a [`MockServer`] does once a client is connected.
+#[derive(Debug, Clone)]
+pub enum Step {
+    Send(String),
+    Wait(Duration),
+}
+
+/// A websocket server on a random local port that plays a script to the
+/// first client accepted on `path`, then keeps the connection open.
+pub struct MockServer {
+    port: u16,
+    received: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// Imitates tosu, which starts sending as soon as a client connects.
+    pub fn tosu(script: Vec<Step>) -> Self {
+        Self::start("/ws", false, script)
+    }
+
+    /// Imitates StreamCompanion, which waits for the list of tokens to send.
+    pub fn stream_companion(script: Vec<Step>) -> Self {
+        Self::start("/tokens", true, script)
+    }
+
+    // Tungstenite decides the type of the rejection.
+    #[allow(clippy::result_large_err)]
+    fn start(path: &'static str, subscription: bool, script: Vec<Step>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind mock server");
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let log = received.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let check_path = |request: &Request, response: Response| {
+                    if request.uri().path() == path {
+                        Ok(response)
+                    } else {
+                        let mut error = ErrorResponse::new(None);
+                        *error.status_mut() = StatusCode::NOT_FOUND;
+                        Err(error)
+                    }
+                };
+
+                let Ok(mut socket) = tungstenite::accept_hdr(stream, check_path) else {
+                    continue;
+                };
+
+                let record = |msg| {
+                    if let Message::Text(text) = msg {
+                        log.lock().unwrap().push(text.as_str().to_string());
+                    }
+                };
+
+                if subscription {
+                    match socket.read() {
+                        Ok(msg) => record(msg),
+                        Err(_) => continue,
+                    }
+                }
+
+                for step in &script {
+                    match step {
+                        Step::Send(payload) => {
+                            if socket.send(Message::Text(payload.as_str().into())).is_err() {
+                                break;
+                            }
+                        }
+                        Step::Wait(duration) => std::thread::sleep(*duration),
+                    }
+                }
+
+                while let Ok(msg) = socket.read() {
+                    record(msg);
+                }
+                return;
+            }
+        });
+
+        Self { port, received }
+    }
+
+    /// The text messages the client sent so far.
+    pub fn received(&self) -> Vec<String> {
+        self.received.lock().unwrap().clone()
+    }
+
+    /// A config that connects `provider` to this server and uses the mock
+    /// display.
+    pub fn config(&self, provider: &str) -> Config {
+        let mut config = Config {
+            provider: provider.to_string(),
+            ..Config::default()
+        };
+
+        let provider = provider::find(provider).expect("Unknown provider");
+        let default = provider.default_endpoint();
+        *config.endpoint_mut(provider) = Endpoint::local(self.port, &default.path);
+        config.display.backend = DisplayBackend::Mock;
+
+        config
+    }
+}
+
+/// A [`Core`] on its own thread, with an in-memory history.
+pub struct TestCore {
+    /// Commands to the core, also keeps the channel open.
+    pub tx: Sender<ChannelMsg>,
+    pub rx: Receiver<ChannelMsg>,
+    pub bus: MockBus,
+}
+
+impl TestCore {
+    pub fn start(config: Config) -> Self {
+        let (tx, core_rx) = mpsc::channel();
+        let (core_tx, rx) = mpsc::channel();
+        let bus = MockBus::default();
+
+        Core::spawn(
+            core_rx,
+            core_tx,
+            config,
+            Arc::default(),
+            History::open_in_memory().ok(),
+            bus.clone(),
+        );
+
+        Self { tx, rx, bus }
+    }
+
+    /// Waits until the display RAM is `expected`, returning whether it got there.
+    pub fn wait_for_ram(&self, expected: [u8; 16]) -> bool {
+        let deadline = Instant::now() + TIMEOUT;
+
+        while Instant::now() < deadline {
+            if self.bus.ram() == expected {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        false
+    }
+
+    /// Waits for a message from the core that `matches`, skipping others.
+    pub fn wait_for_msg(&self, matches: impl Fn(&ChannelMsg) -> bool) -> Option<ChannelMsg> {
+        let deadline = Instant::now() + TIMEOUT;
+
+        while let Ok(msg) = self
+            .rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            if matches(&msg) {
+                return Some(msg);
+            }
+        }
+
+        None
+    }
+}
+
+/// The display RAM after `draw` wrote into the buffer of a fresh display.
+pub fn render(draw: impl FnOnce(&mut Osu7Display<DisplayBus>)) -> [u8; 16] {
+    let bus = MockBus::default();
+    let mut disp = Osu7Display::new(DisplayBus::Mock(bus.clone()), osu7_i2c::I2C_ADDR);
+
+    draw(&mut disp);
+    disp.commit_buffer().expect("Failed to commit buffer");
+
+    bus.ram()
+}
@@ -7,11 +7,11 @@ fn main() {
     let mut display = Osu7Display::new(i2c, I2C_ADDR);
 
     println!("Initializing display.");
-    display.initialize();
+    display.initialize().expect("Failed to initialize display");
 
     println!("Sleeping for 10 seconds...");
     std::thread::sleep(std::time::Duration::from_secs(10));
 
     println!("Turning off display.");
-    display.shutdown();
+    display.shutdown().expect("Failed to turn off display");
 }
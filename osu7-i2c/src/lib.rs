@@ -33,11 +33,12 @@ where
         self.dev.destroy();
     }
 
-    pub fn shutdown(mut self) {
-        self.dev
-            .set_display(Display::OFF)
-            .expect("Could not turn off the display");
+    /// Turns the display off and releases the bus, even if turning it off
+    /// failed.
+    pub fn shutdown(mut self) -> Result<(), E> {
+        let result = self.dev.set_display(Display::OFF);
         self.dev.destroy();
+        result
     }
 
     pub fn device(&mut self) -> &mut HT16K33<I2C> {
@@ -71,18 +72,14 @@ where
         Ok(())
     }
 
-    pub fn initialize(&mut self) {
-        self.dev.initialize().expect("Failed to initialize HT16K33");
-        self.dev
-            .set_display(Display::ON)
-            .expect("Could not turn on the display");
-        self.dev
-            .set_dimming(Dimming::BRIGHTNESS_MIN)
-            .expect("Could not set dimming");
+    /// Turns the display on and shows the logo.
+    pub fn initialize(&mut self) -> Result<(), E> {
+        self.dev.initialize()?;
+        self.dev.set_display(Display::ON)?;
+        self.dev.set_dimming(Dimming::BRIGHTNESS_MIN)?;
 
-        self.write_buffer_osu7()
-            .expect("Failed to write default buffer");
-        self.commit_buffer().expect("Failed to commit buffer");
+        self.write_buffer_osu7()?;
+        self.commit_buffer()
     }
 
     pub fn write_chars(&mut self, chars: [Option<AsciiChar>; 4]) {
@@ -111,6 +108,85 @@ where
         }
     }
 
+    /// Write up to 4 characters into the display buffer, left aligned.
+    /// Characters the display can't show are left blank.
+    pub fn write_buffer_text(&mut self, text: &str) {
+        self.dev.clear_display_buffer();
+
+        let indices = [Index::One, Index::Two, Index::Three, Index::Four];
+        for (index, character) in indices.into_iter().zip(text.chars()) {
+            if character.is_ascii() {
+                let _ = self
+                    .dev
+                    .update_buffer_with_char(index, AsciiChar::new(character));
+            }
+        }
+    }
+
+    /// Write a duration as `M:SS` into the display buffer, or `MM:SS` from
+    /// 10 minutes on. Durations past 99:59 are clamped.
+    pub fn write_buffer_time(&mut self, seconds: u32) {
+        self.dev.clear_display_buffer();
+
+        let minutes = (seconds / 60).min(99);
+        let seconds = if seconds / 60 > 99 { 59 } else { seconds % 60 };
+
+        if minutes >= 10 {
+            self.dev
+                .update_buffer_with_digit(Index::One, (minutes / 10) as u8);
+        }
+        self.dev
+            .update_buffer_with_digit(Index::Two, (minutes % 10) as u8);
+        self.dev.update_buffer_with_colon(true);
+        self.dev
+            .update_buffer_with_digit(Index::Three, (seconds / 10) as u8);
+        self.dev
+            .update_buffer_with_digit(Index::Four, (seconds % 10) as u8);
+    }
+
+    /// Write a signed number with up to `decimals` decimals into the display
+    /// buffer, right aligned behind a minus if negative. Decimals are dropped
+    /// until the number fits, numbers that don't fit at all leave the display
+    /// blank.
+    pub fn write_buffer_signed(&mut self, number: f32, decimals: usize) {
+        self.dev.clear_display_buffer();
+
+        let indices = [Index::One, Index::Two, Index::Three, Index::Four];
+
+        for decimals in (0..=decimals).rev() {
+            let digits: Vec<u8> = format!("{:.*}", decimals, number.abs())
+                .chars()
+                .filter_map(|c| c.to_digit(10))
+                .map(|digit| digit as u8)
+                .collect();
+
+            // Don't show a minus in front of a number that rounded to zero.
+            let negative = number < 0.0 && digits.iter().any(|digit| *digit != 0);
+            let width = digits.len() + usize::from(negative);
+            if width > indices.len() {
+                continue;
+            }
+
+            let start = indices.len() - digits.len();
+            if negative {
+                let _ = self
+                    .dev
+                    .update_buffer_with_char(indices[start - 1], AsciiChar::new('-'));
+            }
+
+            for (index, digit) in indices[start..].iter().zip(&digits) {
+                self.dev.update_buffer_with_digit(*index, *digit);
+            }
+
+            if decimals > 0 {
+                self.dev
+                    .update_buffer_with_dot(indices[indices.len() - decimals - 1], true);
+            }
+
+            return;
+        }
+    }
+
     pub fn write_buffer_float(&mut self, float: f32) {
         self.dev.clear_display_buffer();
         self.dev